@@ -0,0 +1,45 @@
+//! Measures how long the first lookup in a theme takes, which loads the theme from every search path.
+//! Compare `cargo bench` with `cargo bench --features parallel` to see the effect of parallel loading.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use icon_loader::IconLoader;
+
+use std::fs;
+
+/// The number of search paths the theme is installed in.
+const SEARCH_PATHS: usize = 16;
+
+/// The number of icon directories of the theme.
+const DIRECTORIES: usize = 500;
+
+fn find_theme(c: &mut Criterion) {
+    let roots: Vec<_> = (0..SEARCH_PATHS)
+        .map(|_| tempfile::tempdir().unwrap())
+        .collect();
+
+    let mut index = String::from("[Icon Theme]\nName=Bench\n");
+    for size in 0..DIRECTORIES {
+        index.push_str(&format!("\n[{0}x{0}/apps]\nSize={0}\nContext=Applications\n", size + 1));
+    }
+
+    for root in &roots {
+        let theme_dir = root.path().join("bench");
+        fs::create_dir_all(&theme_dir).unwrap();
+        fs::write(theme_dir.join("index.theme"), &index).unwrap();
+    }
+
+    c.bench_function("load theme from 16 search paths", |b| {
+        b.iter_batched(
+            || {
+                let mut loader = IconLoader::new("bench", "bench");
+                loader.set_search_paths(roots.iter().map(|root| root.path()));
+                loader
+            },
+            |loader| loader.theme_exists("bench"),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, find_theme);
+criterion_main!(benches);
@@ -1,5 +1,7 @@
 use std::error::Error as StdError;
 use std::fmt;
+#[cfg(feature = "svg")]
+use std::path::PathBuf;
 
 pub use crate::theme_name_provider::error::Error as ProviderError;
 
@@ -15,11 +17,62 @@ pub enum Error {
         theme_name: String,
     },
 
+    /// The theme could not be loaded from any search path, because it is not installed
+    /// or all of its `index.theme` files are broken.
+    ThemeEmpty {
+        /// The given theme name.
+        theme_name: String,
+    },
+
+    /// The theme could not be parsed.
+    InvalidTheme {
+        /// The given theme name.
+        theme_name: String,
+        /// The source for the error.
+        source: Box<dyn StdError + Send + Sync>,
+    },
+
+    /// No icon with the given name could be found.
+    IconNotFound {
+        /// The given icon name.
+        icon_name: String,
+    },
+
     /// Error updating the default theme name.
     ThemeNameProvider {
         /// The source for the error.
         source: ProviderError,
     },
+
+    /// Error watching the search paths for changes.
+    #[cfg(feature = "watch")]
+    Watch {
+        /// The source for the error.
+        source: notify::Error,
+    },
+
+    /// Error reading a zip archive.
+    #[cfg(feature = "zip")]
+    Archive {
+        /// The source for the error.
+        source: zip::result::ZipError,
+    },
+
+    /// The icon file is not a symbolic SVG icon.
+    #[cfg(feature = "svg")]
+    NotSymbolic {
+        /// The path of the icon file, or `None` if its contents are held in memory.
+        path: Option<PathBuf>,
+    },
+
+    /// Error rendering an icon file.
+    #[cfg(feature = "svg")]
+    Render {
+        /// The path of the icon file, or `None` if its contents are held in memory.
+        path: Option<PathBuf>,
+        /// The source for the error.
+        source: Box<dyn StdError + Send + Sync>,
+    },
 }
 
 impl Error {
@@ -28,12 +81,31 @@ impl Error {
             theme_name: theme_name.into(),
         }
     }
+
+    pub(crate) fn theme_empty(theme_name: impl Into<String>) -> Self {
+        Error::ThemeEmpty {
+            theme_name: theme_name.into(),
+        }
+    }
+
+    pub(crate) fn icon_not_found(icon_name: impl Into<String>) -> Self {
+        Error::IconNotFound {
+            icon_name: icon_name.into(),
+        }
+    }
 }
 
 impl StdError for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
+            Error::InvalidTheme { source, .. } => Some(source.as_ref()),
             Error::ThemeNameProvider { source } => Some(source),
+            #[cfg(feature = "watch")]
+            Error::Watch { source } => Some(source),
+            #[cfg(feature = "zip")]
+            Error::Archive { source } => Some(source),
+            #[cfg(feature = "svg")]
+            Error::Render { source, .. } => Some(source.as_ref()),
             _ => None,
         }
     }
@@ -45,15 +117,58 @@ impl fmt::Display for Error {
             Error::ThemeNotFound { theme_name } => {
                 write!(f, "Theme with name {} not found", theme_name)
             }
+            Error::ThemeEmpty { theme_name } => {
+                write!(f, "Theme with name {} could not be loaded", theme_name)
+            }
+            Error::InvalidTheme { theme_name, source } => {
+                write!(f, "Theme with name {} is invalid: {}", theme_name, source)
+            }
+            Error::IconNotFound { icon_name } => {
+                write!(f, "Icon with name {} not found", icon_name)
+            }
             Error::ThemeNameProvider { source } => {
                 write!(f, "Error updating default theme name: {}", source)
             }
+            #[cfg(feature = "watch")]
+            Error::Watch { source } => write!(f, "Error watching search paths: {}", source),
+            #[cfg(feature = "zip")]
+            Error::Archive { source } => write!(f, "Error reading zip archive: {}", source),
+            #[cfg(feature = "svg")]
+            Error::NotSymbolic { path } => {
+                write!(f, "Icon file {} is not a symbolic SVG icon", display_path(path))
+            }
+            #[cfg(feature = "svg")]
+            Error::Render { path, source } => {
+                write!(f, "Error rendering icon file {}: {}", display_path(path), source)
+            }
         }
     }
 }
 
+/// Displays the path of an icon file whose contents may be held in memory.
+#[cfg(feature = "svg")]
+fn display_path(path: &Option<PathBuf>) -> std::path::Display<'_> {
+    path.as_deref()
+        .unwrap_or(std::path::Path::new("<in memory>"))
+        .display()
+}
+
 impl From<ProviderError> for Error {
     fn from(source: ProviderError) -> Self {
         Error::ThemeNameProvider { source }
     }
 }
+
+#[cfg(feature = "watch")]
+impl From<notify::Error> for Error {
+    fn from(source: notify::Error) -> Self {
+        Error::Watch { source }
+    }
+}
+
+#[cfg(feature = "zip")]
+impl From<zip::result::ZipError> for Error {
+    fn from(source: zip::result::ZipError) -> Self {
+        Error::Archive { source }
+    }
+}
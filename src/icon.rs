@@ -1,18 +1,32 @@
 mod icon_dir;
 mod icon_file;
 pub mod icon_theme;
+#[cfg(feature = "image")]
+mod natural_size;
+#[cfg(feature = "svg")]
+mod symbolic;
 
 pub use icon_dir::{IconDir, IconSizeType};
 pub use icon_file::{IconFile, IconFileType};
 
-pub(crate) use icon_theme::IconThemeChain;
+pub use icon_theme::{IconTheme, IconThemeChain};
+
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+};
 
 /// Struct containing information about a themed icon.
-#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+/// Icons compare equal if their names and files are equal, regardless of the loader they came from.
+#[derive(Clone)]
 pub struct Icon {
     icon_name: String,
     theme_name: String,
     files: Vec<IconFile>,
+    // The context priority of the loader, which only affects file selection.
+    context_priority: Vec<String>,
 }
 
 impl Icon {
@@ -31,10 +45,61 @@ impl Icon {
         &self.files
     }
 
+    /// Consumes the icon and returns its files without cloning them.
+    pub fn into_files(self) -> Vec<IconFile> {
+        self.files
+    }
+
+    /// Consumes the icon and returns its icon name, theme name and files without cloning them.
+    /// The context priority set with [`IconLoader::set_context_priority()`](crate::IconLoader::set_context_priority)
+    /// is not part of them.
+    pub fn into_parts(self) -> (String, String, Vec<IconFile>) {
+        (self.icon_name, self.theme_name, self.files)
+    }
+
+    /// Returns the distinct `(size, scale)` pairs offered by the icon files, sorted ascending.
+    pub fn size_scale_pairs(&self) -> Vec<(u16, u16)> {
+        let mut pairs: Vec<(u16, u16)> = self
+            .files
+            .iter()
+            .map(|file| (file.size(), file.scale()))
+            .collect();
+
+        pairs.sort_unstable();
+        pairs.dedup();
+
+        pairs
+    }
+
+    /// Returns the distinct nominal sizes of the icon files, sorted ascending.
+    /// Scalable files contribute the `Size` of their directory; use [`Icon::has_scalable()`]
+    /// to find out whether the icon can be rendered at arbitrary sizes.
+    pub fn available_sizes(&self) -> Vec<u16> {
+        let mut sizes: Vec<u16> = self.files.iter().map(IconFile::size).collect();
+
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        sizes
+    }
+
+    /// Returns the distinct scales of the icon files, sorted ascending.
+    pub fn available_scales(&self) -> Vec<u16> {
+        let mut scales: Vec<u16> = self.files.iter().map(IconFile::scale).collect();
+
+        scales.sort_unstable();
+        scales.dedup();
+
+        scales
+    }
+
+    /// Returns `true` if any of the icon files is located in a scalable directory.
+    pub fn has_scalable(&self) -> bool {
+        self.files.iter().any(IconFile::is_scalable)
+    }
+
     /// Returns the file of the associated icon that fits the given size best and has a scale of 1.
-    /// If there is no exact fit available, the next bigger one is chosen.
-    /// If there is no bigger one, the next smaller one is returned.
-    /// If that cannot be found, the scale restriction is ignored.
+    /// See [`Icon::file_for_size_scaled()`] for how the file is chosen.
     ///
     /// # Arguments
     ///
@@ -55,11 +120,22 @@ impl Icon {
         self.file_for_size_scaled(size, 1)
     }
 
+    /// Returns the file of the associated icon that fits the given size best like [`Icon::file_for_size()`],
+    /// but returns `None` instead of panicking if the icon has no files.
+    pub fn try_file_for_size(&self, size: u16) -> Option<&IconFile> {
+        self.try_file_for_size_scaled(size, 1)
+    }
+
     /// Returns the file of the associated icon that fits the given size and scale best.
-    /// If there is no exact fit available, the next bigger size is chosen.
-    /// If there is no bigger fit with the given scale, the next smaller one is returned.
-    /// If no file with the preferred scale can be found, one with the size `size * scale` and scale 1 is looked for.
-    /// If that cannot be found, the scale restriction is ignored.
+    /// This follows the lookup algorithm of the freedesktop icon theme spec:
+    /// If a directory with the given scale matches the size exactly, within its threshold
+    /// or within its scalable range, a file from it is returned. If the thresholds of multiple
+    /// directories contain the size, the directory whose size is closest is preferred.
+    /// Otherwise the file whose directory is closest to `size * scale` pixels is chosen, except that
+    /// a scalable file is preferred if `size * scale` exceeds all fixed and threshold directories,
+    /// so large sizes are not served by upscaled raster images. This does not apply to scalable
+    /// directories that declare a `MaxSize` smaller than `size * scale`.
+    /// Ties are broken by preferring the given scale, then PNG over SVG over XPM files.
     ///
     /// # Arguments
     ///
@@ -78,21 +154,49 @@ impl Icon {
     /// };
     /// ```
     pub fn file_for_size_scaled(&self, size: u16, scale: u16) -> &IconFile {
-        if let Some(file) = self.file_for_size_filtered(size, |file| file.scale() == scale) {
-            return file;
-        }
+        // Icons are never created without files.
+        self.try_file_for_size_scaled(size, scale).unwrap()
+    }
 
-        if let Some(file) = self.file_for_size_filtered(size * scale, |file| file.scale() == 1) {
-            return file;
-        }
+    /// Returns the file of the associated icon that fits the given size and scale best like
+    /// [`Icon::file_for_size_scaled()`], but returns `None` instead of panicking if the icon has no files.
+    pub fn try_file_for_size_scaled(&self, size: u16, scale: u16) -> Option<&IconFile> {
+        self.select_file(size, Some(scale), |_| true, type_order(None))
+    }
+
+    /// Returns the file of the associated icon that fits the given size at a fractional scale best,
+    /// e.g. a scale of 1.5. Since themes only have directories with integer scales, the file that fits
+    /// the pixel size `round(size * scale)` at a scale of 1 best is returned, like in
+    /// [`Icon::file_for_size_scaled()`]. Files from directories with a scale other than 1 are only chosen
+    /// if no directory with a scale of 1 matches the pixel size, by comparing their pixel sizes `size * scale`.
+    /// Scales that are not positive or not finite are treated as 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The ideal size of the returned icon file.
+    /// * `scale` - The fractional scale the icon is displayed at.
+    pub fn file_for_size_fractional(&self, size: u16, scale: f32) -> &IconFile {
+        let scale = if scale.is_finite() && scale > 0.0 { scale } else { 1.0 };
+        // Float to integer casts saturate, so huge sizes are clamped to `u16::MAX`.
+        let pixel_size = (f32::from(size) * scale).round() as u16;
 
-        // If we don't filter, there is always at least one file on disk.
-        self.file_for_size_filtered(size, |_| true).unwrap()
+        self.file_for_size_scaled(pixel_size, 1)
+    }
+
+    /// Returns the file of the associated icon that fits the given size best and lives in a directory
+    /// with the given context, e.g. `"MimeTypes"` or `"Apps"`.
+    /// If no file with that context exists, `None` is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The ideal size of the returned icon file.
+    /// * `context` - The context of the directory the returned icon file lives in.
+    pub fn file_for_size_in_context(&self, size: u16, context: &str) -> Option<&IconFile> {
+        self.file_for_size_filtered(size, |file| file.context() == Some(context))
     }
 
     /// Returns the file of the associated icon that fits the given size best and matches the provided filter.
-    /// If there is no exact fit available, the next bigger one is chosen.
-    /// If there is no bigger one, the next smaller one is returned.
+    /// The file is chosen like in [`Icon::file_for_size_scaled()`], but every file is measured at its own scale.
     /// Use this, if you want only files of type PNG or anything like that.
     ///
     /// # Arguments
@@ -116,39 +220,256 @@ impl Icon {
         size: u16,
         filter: impl Fn(&IconFile) -> bool,
     ) -> Option<&IconFile> {
-        let files = self.files.iter().filter(|&file| filter(file));
+        self.select_file(size, None, filter, type_order(None))
+    }
 
-        // Try to return an exact fit.
-        if let Some(icon_file) = files.clone().find(|file| file.dir_info().size() == size) {
-            return Some(icon_file);
-        }
+    /// Returns the file of the associated icon that fits the given size best like [`Icon::file_for_size_filtered()`],
+    /// but lets `cmp` decide between files that fit equally well, e.g. to prefer SVG files or higher scales.
+    /// `cmp` is only consulted among files of equal size fit, the file it orders first is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use icon_loader::IconLoader;
+    ///
+    /// let loader = IconLoader::new_hicolor();
+    /// if let Some(icon) = loader.load_icon("minimum") {
+    ///     let icon_file = icon.file_for_size_by(32, |a, b| b.scale().cmp(&a.scale()));
+    /// };
+    /// ```
+    pub fn file_for_size_by(
+        &self,
+        size: u16,
+        cmp: impl Fn(&IconFile, &IconFile) -> Ordering,
+    ) -> Option<&IconFile> {
+        self.select_file(size, None, |_| true, cmp)
+    }
 
-        // Try to return a threshold fit.
-        if let Some(icon_file) = files
-            .clone()
-            .filter(|file| file.dir_info().size_type() == IconSizeType::Threshold)
-            .find(|file| {
-                size >= file.dir_info().size() - file.dir_info().threshold()
-                    && size <= file.dir_info().size() + file.dir_info().threshold()
-            })
-        {
-            return Some(icon_file);
+    /// Returns the file of the associated icon that fits the given size best, but only if its size
+    /// deviates from `size` by at most `tolerance_pct` percent. Otherwise `None` is returned.
+    /// Only the nominal [size](IconFile::size()) of a file counts, not the threshold or size range
+    /// of its directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The ideal size of the returned icon file.
+    /// * `tolerance_pct` - The maximum deviation from `size` in percent.
+    pub fn file_within_tolerance(&self, size: u16, tolerance_pct: u8) -> Option<&IconFile> {
+        self.file_for_size_filtered(size, |file| {
+            let distance = u64::from(file.size().abs_diff(size));
+
+            distance * 100 <= u64::from(size) * u64::from(tolerance_pct)
+        })
+    }
+
+    /// Returns the file of the associated icon whose directory has exactly the given size and scale,
+    /// or a scalable directory whose size range contains the size. Otherwise `None` is returned,
+    /// so unlike [`Icon::file_for_size_scaled()`], files that would have to be scaled are never returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The exact size of the returned icon file.
+    /// * `scale` - The exact scale of the returned icon file.
+    pub fn file_for_exact_size(&self, size: u16, scale: u16) -> Option<&IconFile> {
+        self.select_file(
+            size,
+            Some(scale),
+            |file| {
+                let dir_info = file.dir_info();
+
+                file.scale() == scale
+                    && match dir_info.size_type() {
+                        IconSizeType::Scalable => {
+                            (dir_info.min_size()..=dir_info.max_size()).contains(&size)
+                        }
+                        _ => dir_info.size() == size,
+                    }
+            },
+            type_order(None),
+        )
+    }
+
+    /// Returns the file of the associated icon that fits the given size best like [`Icon::file_for_size()`],
+    /// but ignores files that are larger than `max_allowed` pixels, unless no other files exist.
+    /// Scalable files count as small enough if they can be rendered at `max_allowed` pixels or less.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The ideal size of the returned icon file.
+    /// * `max_allowed` - The maximum size of the returned icon file in pixels.
+    pub fn file_for_size_clamped(&self, size: u16, max_allowed: u16) -> &IconFile {
+        let small_enough = |file: &IconFile| {
+            let min_size = match file.size_type() {
+                IconSizeType::Scalable => file.min_size(),
+                _ => file.size(),
+            };
+
+            u32::from(min_size) * u32::from(file.scale()) <= u32::from(max_allowed)
+        };
+
+        self.select_file(size, Some(1), small_enough, type_order(None))
+            .unwrap_or_else(|| self.file_for_size(size))
+    }
+
+    /// Returns the file of the associated icon that fits the given size best like [`Icon::file_for_size()`],
+    /// but only considers files whose size is one of `allowed`.
+    /// If no such file exists, `None` is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The ideal size of the returned icon file.
+    /// * `allowed` - The sizes the returned icon file may have.
+    pub fn file_for_size_within(&self, size: u16, allowed: &[u16]) -> Option<&IconFile> {
+        self.select_file(
+            size,
+            Some(1),
+            |file| allowed.contains(&file.size()),
+            type_order(None),
+        )
+    }
+
+    /// Returns an `Icon` that only contains the file [`Icon::file_for_size()`] returns for `size`.
+    /// Use this to shrink icons that are kept around for a single size.
+    pub fn reduced_to_size(self, size: u16) -> Icon {
+        let file = self.file_for_size(size).clone();
+
+        Icon {
+            files: vec![file],
+            ..self
         }
+    }
 
-        // Try to return a slightly bigger fit.
-        if let Some(icon_file) = files
-            .clone()
-            .filter(|file| file.dir_info().size() > size)
-            .min_by_key(|file| file.dir_info().size())
-        {
-            return Some(icon_file);
+    /// Returns a [`FileQuery`] to select a file by size, scale, type and context at once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use icon_loader::{IconLoader, IconFileType};
+    ///
+    /// let loader = IconLoader::new_hicolor();
+    /// if let Some(icon) = loader.load_icon("minimum") {
+    ///     let icon_file = icon
+    ///         .best_file()
+    ///         .size(32)
+    ///         .scale(2)
+    ///         .prefer_type(IconFileType::SVG)
+    ///         .resolve();
+    /// };
+    /// ```
+    pub fn best_file(&self) -> FileQuery<'_> {
+        FileQuery {
+            icon: self,
+            size: 0,
+            scale: 1,
+            preferred_type: None,
+            context: None,
         }
+    }
 
-        // Return the biggest available.
-        files.max_by_key(|file| file.dir_info().size())
+    /// Selects the file that fits `size` at `scale` best. If `scale` is `None`,
+    /// every file is measured at its own scale. Ties are decided by `tie_break`.
+    fn select_file(
+        &self,
+        size: u16,
+        scale: Option<u16>,
+        filter: impl Fn(&IconFile) -> bool,
+        tie_break: impl Fn(&IconFile, &IconFile) -> Ordering,
+    ) -> Option<&IconFile> {
+        let scale_for = |file: &IconFile| scale.unwrap_or_else(|| file.scale());
+        let pixels = |file: &IconFile| u32::from(size) * u32::from(scale_for(file));
+        let max_pixels = |file: &IconFile| {
+            u32::from(file.dir_info().size_range().1) * u32::from(file.scale())
+        };
+        // Whether the requested size is larger than every file that is not scalable can serve.
+        let exceeds_raster = self
+            .files
+            .iter()
+            .filter(|&file| filter(file) && !file.is_scalable())
+            .all(|file| pixels(file) > max_pixels(file));
+        let size_rank = |file: &IconFile| {
+            if file.dir_info().matches_size(size, scale_for(file)) {
+                // Files from directories that match the size win.
+                // Overlapping threshold directories are ranked by how close their size is.
+                let threshold_distance = match file.size_type() {
+                    IconSizeType::Threshold => file.size().abs_diff(size),
+                    _ => 0,
+                };
+
+                (false, false, u32::from(threshold_distance), false)
+            } else {
+                // Otherwise the file from the closest directory wins,
+                // but scalable files win if no raster file is large enough.
+                (
+                    true,
+                    exceeds_raster && !file.dir_info().can_scale_to(pixels(file)),
+                    file.dir_info().size_distance(size, scale_for(file)),
+                    file.scale() != scale_for(file),
+                )
+            }
+        };
+
+        self.files
+            .iter()
+            .filter(|&file| filter(file))
+            .min_by(|a, b| {
+                size_rank(a)
+                    .cmp(&size_rank(b))
+                    .then_with(|| self.context_rank(a).cmp(&self.context_rank(b)))
+                    .then_with(|| tie_break(a, b))
+            })
+    }
+
+    /// Returns the position of the context of `file` in the context priority,
+    /// or the length of the priority list if its context has no priority.
+    fn context_rank(&self, file: &IconFile) -> usize {
+        self.context_priority
+            .iter()
+            .position(|context| file.context() == Some(context.as_str()))
+            .unwrap_or(self.context_priority.len())
     }
 
-    pub(crate) fn new(icon_name: String, theme_name: String, files: Vec<IconFile>) -> Option<Self> {
+    /// Creates a new `Icon` from a list of icon files, e.g. from a custom icon source.
+    /// Returns `None` if `icon_name` or `theme_name` is empty or `files` contains no files.
+    /// Files of the same type in the same directory as an earlier file are dropped.
+    /// This allows using the file selection of [`Icon::file_for_size()`] and similar methods
+    /// without discovering icon themes on disk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use icon_loader::{Icon, IconDir, IconFile, IconFileType};
+    ///
+    /// let dir = Arc::new(IconDir::with_size("32x32/apps", 32));
+    /// let file = IconFile::from_bytes(dir, &b"<svg/>"[..], IconFileType::SVG);
+    /// let icon = Icon::from_files("app", "my-theme", vec![file]).unwrap();
+    ///
+    /// assert_eq!(icon.file_for_size(32).size(), 32);
+    /// ```
+    pub fn from_files(
+        icon_name: impl Into<String>,
+        theme_name: impl Into<String>,
+        files: Vec<IconFile>,
+    ) -> Option<Self> {
+        Self::new(icon_name.into(), theme_name.into(), files)
+    }
+
+    /// Sets the directory contexts that are preferred, highest priority first. File selection
+    /// prefers files from higher priority contexts among files that fit the size equally well.
+    pub(crate) fn set_context_priority(&mut self, context_priority: &[String]) {
+        self.context_priority = context_priority.to_vec();
+    }
+
+    /// Creates a new `Icon`. Files of the same type in the same directory as an earlier file
+    /// are dropped, so the first occurrence, which has the highest priority, is kept.
+    pub(crate) fn new(
+        icon_name: String,
+        theme_name: String,
+        mut files: Vec<IconFile>,
+    ) -> Option<Self> {
+        let mut seen = HashSet::new();
+        files.retain(|file| seen.insert(file.dedup_key()));
+
         if icon_name.is_empty() || theme_name.is_empty() || files.is_empty() {
             None
         } else {
@@ -156,7 +477,143 @@ impl Icon {
                 files,
                 icon_name,
                 theme_name,
+                context_priority: Vec::new(),
+            })
+        }
+    }
+}
+
+impl fmt::Display for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} @ {} ({} files)", self.icon_name, self.theme_name, self.files.len())
+    }
+}
+
+/// Only summarizes the available sizes instead of listing every file, e.g. `[16, 32@2, scalable]`.
+impl PartialEq for Icon {
+    fn eq(&self, other: &Self) -> bool {
+        self.icon_name == other.icon_name
+            && self.theme_name == other.theme_name
+            && self.files == other.files
+    }
+}
+
+impl Eq for Icon {}
+
+impl Hash for Icon {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.icon_name.hash(state);
+        self.theme_name.hash(state);
+        self.files.hash(state);
+    }
+}
+
+impl fmt::Debug for Icon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Icon")
+            .field("icon_name", &self.icon_name)
+            .field("theme_name", &self.theme_name)
+            .field("sizes", &SizeSummary(&self.files))
+            .finish()
+    }
+}
+
+/// Formats the distinct sizes of icon files, followed by `scalable` if there are scalable files.
+struct SizeSummary<'a>(&'a [IconFile]);
+
+impl fmt::Debug for SizeSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sizes: Vec<(u16, u16)> = self
+            .0
+            .iter()
+            .filter(|file| file.dir_info().size_type() != IconSizeType::Scalable)
+            .map(|file| (file.size(), file.scale()))
+            .collect();
+
+        sizes.sort_unstable();
+        sizes.dedup();
+
+        let mut names: Vec<String> = sizes
+            .into_iter()
+            .map(|(size, scale)| match scale {
+                1 => size.to_string(),
+                _ => format!("{}@{}", size, scale),
             })
+            .collect();
+
+        if self
+            .0
+            .iter()
+            .any(|file| file.dir_info().size_type() == IconSizeType::Scalable)
+        {
+            names.push("scalable".to_string());
         }
+
+        write!(f, "[{}]", names.join(", "))
+    }
+}
+
+/// A query for the file of an [`Icon`] that fits a set of requirements best.
+/// It is created by [`Icon::best_file()`].
+#[derive(Clone, Copy, Debug)]
+pub struct FileQuery<'a> {
+    icon: &'a Icon,
+    size: u16,
+    scale: u16,
+    preferred_type: Option<IconFileType>,
+    context: Option<&'a str>,
+}
+
+impl<'a> FileQuery<'a> {
+    /// Sets the ideal size of the file. The default is 0, which selects the smallest file.
+    pub fn size(mut self, size: u16) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Sets the preferred scale of the file. The default is 1.
+    pub fn scale(mut self, scale: u16) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Prefers files of the given type over other files that fit equally well.
+    /// By default PNG files are preferred over SVG files over XPM files.
+    pub fn prefer_type(mut self, icon_type: IconFileType) -> Self {
+        self.preferred_type = Some(icon_type);
+        self
+    }
+
+    /// Only considers files living in a directory with the given context, e.g. `"Apps"`.
+    pub fn context(mut self, context: &'a str) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Returns the file that fits the query best, chosen like in [`Icon::file_for_size_scaled()`].
+    /// Returns `None` if no file matches the context.
+    pub fn resolve(self) -> Option<&'a IconFile> {
+        self.icon.select_file(
+            self.size,
+            Some(self.scale),
+            |file| self.context.is_none() || file.context() == self.context,
+            type_order(self.preferred_type),
+        )
+    }
+}
+
+/// Orders files of `preferred_type` first, followed by PNG, SVG and XPM files.
+fn type_order(preferred_type: Option<IconFileType>) -> impl Fn(&IconFile, &IconFile) -> Ordering {
+    move |a, b| {
+        let type_rank = |file: &IconFile| {
+            (
+                preferred_type.is_some_and(|icon_type| icon_type != file.icon_type()),
+                IconFileType::types()
+                    .iter()
+                    .position(|icon_type| *icon_type == file.icon_type()),
+            )
+        };
+
+        type_rank(a).cmp(&type_rank(b))
     }
 }
@@ -3,12 +3,14 @@ use std::path::{Path, PathBuf};
 /// Struct that holds information about a directory containing a set of icons
 /// with a particular size.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IconDir {
     path: PathBuf,
     size: u16,
     scale: u16,
     context: Option<String>,
     size_type: IconSizeType,
+    unknown_size_type: Option<String>,
     max_size: Option<u16>,
     min_size: Option<u16>,
     threshold: Option<u16>,
@@ -22,6 +24,7 @@ impl IconDir {
             scale: 1,
             context: None,
             size_type: IconSizeType::Threshold,
+            unknown_size_type: None,
             max_size: None,
             min_size: None,
             threshold: None,
@@ -42,7 +45,20 @@ impl IconDir {
                 "Context" => {
                     dir_info.context = Some(String::from(value));
                 }
-                "Type" => dir_info.size_type = value.into(),
+                "Type" => {
+                    dir_info.size_type = value.into();
+
+                    if !IconSizeType::is_known(value) {
+                        #[cfg(feature = "theme_error_log")]
+                        log::warn!(
+                            "Unknown Type {} of directory {}, assuming Threshold",
+                            value,
+                            dir_info.path.display()
+                        );
+
+                        dir_info.unknown_size_type = Some(String::from(value));
+                    }
+                }
                 "Threshold" => {
                     if let Ok(threshold) = value.parse() {
                         dir_info.threshold = Some(threshold);
@@ -65,6 +81,70 @@ impl IconDir {
         dir_info
     }
 
+    /// Creates the info of a directory with icons of the given nominal size, without reading
+    /// an `index.theme` file. Like in a theme without further keys, the directory has a scale of 1,
+    /// no context and the size type [`IconSizeType::Threshold`]. Use the `with_*` methods to change these.
+    pub fn with_size(path: impl Into<PathBuf>, size: u16) -> Self {
+        Self {
+            path: path.into(),
+            size,
+            scale: 1,
+            context: None,
+            size_type: IconSizeType::Threshold,
+            unknown_size_type: None,
+            max_size: None,
+            min_size: None,
+            threshold: None,
+        }
+    }
+
+    /// Sets the scale of the directory.
+    pub fn with_scale(mut self, scale: u16) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the context of the directory, e.g. `"Apps"`.
+    pub fn with_context(mut self, context: impl Into<String>) -> Self {
+        self.context = Some(context.into());
+        self
+    }
+
+    /// Sets the size type of the directory.
+    pub fn with_size_type(mut self, size_type: IconSizeType) -> Self {
+        self.size_type = size_type;
+        self
+    }
+
+    /// Sets the minimum and maximum size of a scalable directory.
+    pub fn with_size_range(mut self, min_size: u16, max_size: u16) -> Self {
+        self.min_size = Some(min_size);
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// Sets the threshold of a threshold directory.
+    pub fn with_threshold(mut self, threshold: u16) -> Self {
+        self.threshold = Some(threshold);
+        self
+    }
+
+    /// Creates the info of a flat directory without theme structure, like `/usr/share/pixmaps`.
+    /// Since the size of its icons is unknown, it has a size of 0 and is scalable.
+    pub(crate) fn flat(path: PathBuf) -> Self {
+        Self {
+            path,
+            size: 0,
+            scale: 1,
+            context: None,
+            size_type: IconSizeType::Scalable,
+            unknown_size_type: None,
+            max_size: None,
+            min_size: None,
+            threshold: None,
+        }
+    }
+
     /// Returns the path of this icon dir.
     pub fn path(&self) -> &Path {
         &self.path
@@ -90,6 +170,12 @@ impl IconDir {
         self.size_type
     }
 
+    /// Returns the value of the `Type` key, if it is none of `Fixed`, `Scalable` or `Threshold`.
+    /// Such directories are treated as [`IconSizeType::Threshold`].
+    pub fn unknown_size_type(&self) -> Option<&str> {
+        self.unknown_size_type.as_deref()
+    }
+
     /// Returns the max size of icons contained.
     pub fn max_size(&self) -> u16 {
         self.max_size.unwrap_or_else(|| self.size())
@@ -105,12 +191,60 @@ impl IconDir {
         self.threshold.unwrap_or(2)
     }
 
+    /// Returns whether icons of the given size and scale can be served by this dir,
+    /// following the `DirectoryMatchesSize` function of the freedesktop icon theme spec.
+    /// Fixed dirs match their size, scalable dirs match `MinSize` to `MaxSize` and threshold dirs
+    /// match their size plus or minus the threshold. The scale has to match exactly.
+    pub fn matches_size(&self, size: u16, scale: u16) -> bool {
+        let (min_size, max_size) = self.size_range();
+
+        self.scale == scale && min_size <= size && size <= max_size
+    }
+
+    /// Returns how far icons of this dir are from the given size and scale in pixels,
+    /// following the `DirectorySizeDistance` function of the freedesktop icon theme spec.
+    /// Returns 0 if the dir can serve `size * scale` pixels.
+    pub fn size_distance(&self, size: u16, scale: u16) -> u32 {
+        let (min_size, max_size) = self.size_range();
+        let requested = u32::from(size) * u32::from(scale);
+        let min_size = u32::from(min_size) * u32::from(self.scale);
+        let max_size = u32::from(max_size) * u32::from(self.scale);
+
+        if requested < min_size {
+            min_size - requested
+        } else {
+            requested.saturating_sub(max_size)
+        }
+    }
+
+    /// Returns whether icons of this dir may be rendered at `pixels` pixels,
+    /// which scalable dirs allow unless they declare a smaller `MaxSize`.
+    pub(crate) fn can_scale_to(&self, pixels: u32) -> bool {
+        self.size_type == IconSizeType::Scalable
+            && self
+                .max_size
+                .is_none_or(|max_size| pixels <= u32::from(max_size) * u32::from(self.scale))
+    }
+
+    /// Returns the smallest and largest size icons of this dir can be used for without scaling.
+    pub(crate) fn size_range(&self) -> (u16, u16) {
+        match self.size_type {
+            IconSizeType::Fixed => (self.size, self.size),
+            IconSizeType::Scalable => (self.min_size(), self.max_size()),
+            IconSizeType::Threshold => (
+                self.size.saturating_sub(self.threshold()),
+                self.size.saturating_add(self.threshold()),
+            ),
+        }
+    }
+
     pub(crate) const fn is_valid(&self) -> bool {
         self.size != 0
     }
 }
 
 #[derive(Clone, Copy, Hash, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 /// The size type of icons contained in an [`IconDir`](crate::icon::IconDir).
 pub enum IconSizeType {
     /// Icons with a fixed size.
@@ -123,6 +257,12 @@ pub enum IconSizeType {
     Threshold,
 }
 
+impl IconSizeType {
+    fn is_known(s: &str) -> bool {
+        matches!(s, "Fixed" | "Scalable" | "Threshold")
+    }
+}
+
 impl<S: AsRef<str>> From<S> for IconSizeType {
     fn from(s: S) -> Self {
         match s.as_ref() {
@@ -1,13 +1,14 @@
 use super::{IconDir, IconSizeType};
 
 use std::{
-    borrow::Borrow,
+    fmt, fs, io,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
 /// Enum representing the different file types an icon can be.
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IconFileType {
     /// PNG file type
     PNG,
@@ -25,6 +26,19 @@ impl IconFileType {
 
         &TYPES
     }
+
+    /// Returns the file type with the extension `ext`, e.g. `"png"`, ignoring ASCII case.
+    pub fn from_extension(ext: &str) -> Option<IconFileType> {
+        Self::types()
+            .iter()
+            .find(|icon_type| icon_type.as_ref().eq_ignore_ascii_case(ext))
+            .copied()
+    }
+
+    /// Returns the file type of the file at `path` based on its extension, ignoring ASCII case.
+    pub fn from_path(path: &Path) -> Option<IconFileType> {
+        Self::from_extension(path.extension()?.to_str()?)
+    }
 }
 
 impl AsRef<str> for IconFileType {
@@ -37,11 +51,33 @@ impl AsRef<str> for IconFileType {
     }
 }
 
-/// Struct containing information about a single icon file on disk.
+/// Where the contents of an icon file come from.
+#[derive(Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum IconSource {
+    /// A file on disk.
+    File(PathBuf),
+
+    /// Contents held in memory, e.g. compiled into the binary.
+    Bytes(Arc<[u8]>),
+}
+
+impl fmt::Debug for IconSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IconSource::File(path) => f.debug_tuple("File").field(path).finish(),
+            IconSource::Bytes(data) => write!(f, "Bytes({} bytes)", data.len()),
+        }
+    }
+}
+
+/// Struct containing information about a single icon file,
+/// either on disk or held in memory.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IconFile {
     dir_info: Arc<IconDir>,
-    path: PathBuf,
+    source: IconSource,
     icon_type: IconFileType,
 }
 
@@ -51,9 +87,44 @@ impl IconFile {
         &self.dir_info
     }
 
-    /// Returns this icon's path.
-    pub fn path(&self) -> &Path {
-        &self.path
+    /// Returns this icon's path, or `None` if its contents are held in memory.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.source {
+            IconSource::File(path) => Some(path),
+            IconSource::Bytes(_) => None,
+        }
+    }
+
+    /// Returns this icon's path with all symlinks resolved, which identifies the file on disk
+    /// even if several theme entries link to it. [`IconFile::path()`] returns the path it was found at.
+    /// Returns an error of kind [`io::ErrorKind::NotFound`] if the contents are held in memory.
+    pub fn canonical_path(&self) -> io::Result<PathBuf> {
+        match &self.source {
+            IconSource::File(path) => fs::canonicalize(path),
+            IconSource::Bytes(_) => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "icon file is held in memory",
+            )),
+        }
+    }
+
+    /// Returns the search path the theme of this icon file was found in, i.e. the parent of the
+    /// theme directory, e.g. `~/.icons` or `/usr/share/icons`. This tells which search path won
+    /// if a theme is installed in several of them.
+    /// Returns `None` if the file is held in memory or does not belong to a theme directory.
+    pub fn theme_root(&self) -> Option<&Path> {
+        // The path is `<theme root>/<theme>/<icon dir>/<file name>`.
+        let depth = self.dir_info.path().components().count() + 2;
+
+        self.path()?.ancestors().nth(depth)
+    }
+
+    /// Reads the contents of this icon file, from disk or from memory.
+    pub fn read(&self) -> io::Result<Vec<u8>> {
+        match &self.source {
+            IconSource::File(path) => fs::read(path),
+            IconSource::Bytes(data) => Ok(data.to_vec()),
+        }
     }
 
     /// Returns this icon's type.
@@ -81,6 +152,18 @@ impl IconFile {
         self.dir_info.size_type()
     }
 
+    /// Returns whether this icon is located in a scalable directory, e.g. because it is an SVG file
+    /// that can be rendered at any size within its directory's size range.
+    pub fn is_scalable(&self) -> bool {
+        self.dir_info.size_type() == IconSizeType::Scalable
+    }
+
+    /// Returns the key under which files are considered duplicates: files of the same type
+    /// in directories with the same path and size parameters.
+    pub(crate) fn dedup_key(&self) -> (IconDir, IconFileType) {
+        ((*self.dir_info).clone(), self.icon_type)
+    }
+
     /// Returns this icon's max size.
     pub fn max_size(&self) -> u16 {
         self.dir_info.max_size()
@@ -96,27 +179,31 @@ impl IconFile {
         self.dir_info.threshold()
     }
 
-    pub(crate) const fn new(
+    /// Creates a new `IconFile` living in the directory `dir_info` at `path`.
+    /// The file is not required to exist, which allows building icons from custom sources.
+    pub const fn new(
         dir_info: Arc<IconDir>,
         path: PathBuf,
         icon_type: IconFileType,
     ) -> Self {
         Self {
             dir_info,
-            path,
+            source: IconSource::File(path),
             icon_type,
         }
     }
-}
-
-impl AsRef<Path> for IconFile {
-    fn as_ref(&self) -> &Path {
-        self.path()
-    }
-}
 
-impl Borrow<Path> for IconFile {
-    fn borrow(&self) -> &Path {
-        self.path()
+    /// Creates a new `IconFile` living in the directory `dir_info`, whose contents `data` are held in memory.
+    /// Such a file has no path.
+    pub fn from_bytes(
+        dir_info: Arc<IconDir>,
+        data: impl Into<Arc<[u8]>>,
+        icon_type: IconFileType,
+    ) -> Self {
+        Self {
+            dir_info,
+            source: IconSource::Bytes(data.into()),
+            icon_type,
+        }
     }
 }
@@ -1,20 +1,49 @@
 pub mod error;
+#[cfg(feature = "gtk-cache")]
+mod icon_cache;
 
 pub use error::{Error, Result};
 
 use super::{Icon, IconDir, IconFile, IconFileType};
 
-use std::{path::PathBuf, sync::Arc};
-use crate::ThemeCache;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+};
+use crate::{loader::ThemeOptions, ThemeCache};
+use dashmap::DashSet;
+#[cfg(feature = "gtk-cache")]
+use icon_cache::IconCache;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
+/// The maximum number of icon names a theme chain remembers as missing.
+const MAX_CACHED_MISSES: usize = 1024;
+
+/// The names, types and contents of icon files held in memory, grouped by the directory they live in.
+type MemoryFiles = HashMap<PathBuf, Vec<(String, IconFileType, Arc<[u8]>)>>;
+
+/// A single installation of an icon theme, i.e. the theme in one search path.
 #[derive(Debug)]
 pub struct IconTheme {
     pub content_dir: PathBuf,
     key_list: Vec<Arc<IconDir>>,
+    hidden: bool,
+    memory_files: Option<MemoryFiles>,
+    #[cfg(feature = "gtk-cache")]
+    icon_cache: Option<IconCache>,
 }
 
 impl IconTheme {
-    fn from_dir(content_dir: PathBuf, parents: &mut Vec<String>) -> Result<Self> {
+    /// Loads the theme in `content_dir`. If `lenient` is true, lines of the `index.theme` file
+    /// that cannot be parsed are skipped instead of failing.
+    pub(crate) fn from_dir(
+        content_dir: PathBuf,
+        parents: &mut Vec<String>,
+        lenient: bool,
+    ) -> Result<Self> {
         if !content_dir.is_dir() {
             return Err(Error::NotDirectory(content_dir));
         }
@@ -24,95 +53,463 @@ impl IconTheme {
             return Err(Error::IndexThemeNotFound(theme_index_path));
         }
 
+        let ini = match ini::Ini::load_from_file(&theme_index_path) {
+            Err(ini::Error::Parse(_)) if lenient => {
+                let index = fs::read_to_string(&theme_index_path).map_err(ini::Error::Io)?;
+
+                parse_lenient(&index, &theme_index_path)?
+            }
+            ini => ini?,
+        };
+
+        #[cfg_attr(not(feature = "gtk-cache"), allow(unused_mut))]
+        let mut theme = Self::from_ini(content_dir, &ini, parents)?;
+
+        #[cfg(feature = "gtk-cache")]
+        {
+            let dirs = theme.key_list.iter().map(|dir| dir.path());
+            theme.icon_cache = IconCache::load(&theme.content_dir, dirs);
+        }
+
+        Ok(theme)
+    }
+
+    /// Parses the contents of an `index.theme` file for a theme whose icons live in `content_dir`.
+    pub(crate) fn from_index(
+        content_dir: PathBuf,
+        index: &str,
+        parents: &mut Vec<String>,
+    ) -> Result<Self> {
+        let ini = ini::Ini::load_from_str(index).map_err(ini::Error::Parse)?;
+
+        Self::from_ini(content_dir, &ini, parents)
+    }
+
+    fn from_ini(content_dir: PathBuf, ini: &ini::Ini, parents: &mut Vec<String>) -> Result<Self> {
         let mut theme = Self {
             content_dir,
             key_list: Vec::new(),
+            hidden: false,
+            memory_files: None,
+            #[cfg(feature = "gtk-cache")]
+            icon_cache: None,
         };
+        let mut inherits = false;
+        let mut declared_dirs: Option<Vec<&str>> = None;
 
-        let ini = ini::Ini::load_from_file(theme_index_path)?;
-
-        for (dir_key, properties) in ini.iter() {
-            if let Some(dir_key) = dir_key {
-                match dir_key {
-                    "Icon Theme" => {
-                        for (key, value) in properties.iter() {
-                            if key == "Inherits" {
-                                for parent in value.split(',').map(str::trim).map(String::from) {
-                                    if !parents.contains(&parent) {
-                                        parents.push(parent);
-                                    }
-                                }
+        if let Some(properties) = ini.section(Some("Icon Theme")) {
+            for (key, value) in properties.iter() {
+                match key.trim() {
+                    "Inherits" => {
+                        for parent in value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|parent| !parent.is_empty())
+                            .map(String::from)
+                        {
+                            inherits = true;
+
+                            if !parents.contains(&parent) {
+                                parents.push(parent);
                             }
                         }
                     }
-                    _ => {
-                        let dir_info = IconDir::new(dir_key.into(), properties);
+                    "Directories" | "ScaledDirectories" => {
+                        let dirs = declared_dirs.get_or_insert_with(Vec::new);
 
-                        if dir_info.is_valid() {
-                            theme.key_list.push(Arc::new(dir_info));
+                        for dir in value.split(',').map(str::trim).filter(|dir| !dir.is_empty()) {
+                            if !dirs.contains(&dir) {
+                                dirs.push(dir);
+                            }
                         }
                     }
+                    "Hidden" => theme.hidden = value.trim() == "true",
+                    _ => {}
                 }
             }
         }
 
-        if theme.key_list.is_empty() {
+        match declared_dirs {
+            // Only the directories listed by `Directories` and `ScaledDirectories` belong to the theme.
+            Some(declared_dirs) => {
+                for dir_key in declared_dirs {
+                    let properties = ini.section(Some(dir_key));
+
+                    #[cfg(feature = "theme_error_log")]
+                    if properties.is_none() {
+                        log::warn!(
+                            "Directory {} of theme {} has no section",
+                            dir_key,
+                            theme.content_dir.display()
+                        );
+                    }
+
+                    if let Some(properties) = properties {
+                        theme.push_dir(dir_key, properties);
+                    }
+                }
+            }
+            // Themes that do not declare their directories use every section.
+            None => {
+                for (dir_key, properties) in ini.iter() {
+                    if let Some(dir_key) = dir_key.filter(|dir_key| *dir_key != "Icon Theme") {
+                        theme.push_dir(dir_key, properties);
+                    }
+                }
+            }
+        }
+
+        // Pointer themes like `default` have no directories and only inherit from the actual theme.
+        if theme.key_list.is_empty() && !inherits {
             return Err(Error::KeyListEmpty(theme.content_dir));
         }
 
         Ok(theme)
     }
 
+    /// Adds the directory `dir_key` with the given properties, if it is valid.
+    fn push_dir(&mut self, dir_key: &str, properties: &ini::Properties) {
+        let dir_info = IconDir::new(dir_key.into(), properties);
+
+        if dir_info.is_valid() {
+            self.key_list.push(Arc::new(dir_info));
+        }
+    }
+
+    /// Loads a theme compiled into the binary from the embedded directory `dir`.
+    #[cfg(feature = "embed")]
+    pub(crate) fn from_embedded(
+        dir: &'static include_dir::Dir<'static>,
+        parents: &mut Vec<String>,
+    ) -> Result<Self> {
+        let mut files = Vec::new();
+        let mut dirs = vec![dir];
+
+        while let Some(dir) = dirs.pop() {
+            files.extend(
+                dir.files()
+                    .map(|file| (file.path().to_path_buf(), file.contents().into())),
+            );
+            dirs.extend(dir.dirs());
+        }
+
+        Self::from_memory(dir.path().to_path_buf(), files, parents)
+    }
+
+    /// Loads a theme whose files are held in memory. `files` maps the paths of all files,
+    /// including `index.theme`, to their contents. The theme lives in `content_dir`.
+    #[cfg(any(feature = "embed", feature = "zip"))]
+    pub(crate) fn from_memory(
+        content_dir: PathBuf,
+        files: impl IntoIterator<Item = (PathBuf, Arc<[u8]>)>,
+        parents: &mut Vec<String>,
+    ) -> Result<Self> {
+        let theme_index_path = content_dir.join("index.theme");
+        let mut index = None;
+        let mut memory_files = MemoryFiles::new();
+
+        for (path, data) in files {
+            if path == theme_index_path {
+                index = Some(data);
+            } else if let (Some(dir_path), Some((name, icon_type))) =
+                (path.parent(), icon_name_and_type(&path))
+            {
+                memory_files
+                    .entry(dir_path.to_path_buf())
+                    .or_default()
+                    .push((name, icon_type, data));
+            }
+        }
+
+        let index = index.ok_or(Error::IndexThemeNotFound(theme_index_path))?;
+        let mut theme = Self::from_index(content_dir, &String::from_utf8_lossy(&index), parents)?;
+        theme.memory_files = Some(memory_files);
+
+        Ok(theme)
+    }
+
+    /// Returns the directory of this installation of the theme, which contains its `index.theme` file.
+    /// Themes held in memory return the path their files were stored under.
+    pub fn content_dir(&self) -> &Path {
+        &self.content_dir
+    }
+
+    /// Returns whether the theme sets `Hidden=true`, i.e. should not be offered to users.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Returns the icon directories declared by the theme, in the order of its `Directories` key.
     pub fn dirs(&self) -> &[Arc<IconDir>] {
         &self.key_list
     }
-    
+
+    /// Returns the icon directories declared by the theme, like [`IconTheme::dirs()`].
+    pub fn directories(&self) -> &[Arc<IconDir>] {
+        self.dirs()
+    }
+
+    /// Returns the sorted names of all icons of this theme.
+    pub fn icon_names(&self) -> Vec<String> {
+        self.icon_names_filtered(|_| true)
+    }
+
+    /// Returns the sorted names of all icons in directories with the given context.
+    pub fn icon_names_in_context(&self, context: &str) -> Vec<String> {
+        self.icon_names_filtered(|dir| dir.context() == Some(context))
+    }
+
+    fn icon_names_filtered(&self, filter: impl Fn(&IconDir) -> bool) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .iter_entries_filtered(filter)
+            .map(|(name, _)| name)
+            .collect();
+
+        names.sort_unstable();
+        names.dedup();
+
+        names
+    }
+
+    /// Returns an iterator over all icon files of this theme together with their icon names.
+    /// The directories of the theme are read lazily while iterating.
+    pub fn iter_entries(&self) -> impl Iterator<Item = (String, IconFile)> + '_ {
+        self.iter_entries_filtered(|_| true)
+    }
+
+    /// Returns an iterator over the icon files of this theme that live in directories with
+    /// the given scale, together with their icon names.
+    pub fn iter_entries_scaled(&self, scale: u16) -> impl Iterator<Item = (String, IconFile)> + '_ {
+        self.iter_entries_filtered(move |icon_dir_info| icon_dir_info.scale() == scale)
+    }
+
+    fn iter_entries_filtered<'a>(
+        &'a self,
+        filter: impl Fn(&IconDir) -> bool + 'a,
+    ) -> impl Iterator<Item = (String, IconFile)> + 'a {
+        self.key_list
+            .iter()
+            .filter(move |icon_dir_info| filter(icon_dir_info))
+            .flat_map(move |icon_dir_info| self.dir_files(icon_dir_info))
+    }
+
+    /// Returns an iterator over all icon files in the directory `icon_dir_info` of this theme
+    /// together with their icon names. The directory is read lazily while iterating.
+    fn dir_files<'a>(
+        &'a self,
+        icon_dir_info: &'a Arc<IconDir>,
+    ) -> Box<dyn Iterator<Item = (String, IconFile)> + 'a> {
+        let dir_path = self.content_dir.join(icon_dir_info.path());
+
+        match &self.memory_files {
+            Some(memory_files) => Box::new(memory_files.get(&dir_path).into_iter().flatten().map(
+                move |(name, icon_type, data)| {
+                    let file = IconFile::from_bytes(icon_dir_info.clone(), data.clone(), *icon_type);
+
+                    (name.clone(), file)
+                },
+            )),
+            None => Box::new(fs::read_dir(dir_path).into_iter().flatten().flatten().filter_map(
+                move |entry| {
+                    let path = entry.path();
+                    let (name, icon_type) = icon_name_and_type(&path)?;
+
+                    Some((name, IconFile::new(icon_dir_info.clone(), path, icon_type)))
+                },
+            )),
+        }
+    }
+
+    /// Returns the icon file named `icon_name` of the given type in the directory `icon_dir_info`, if it exists.
+    fn file_at(&self, icon_dir_info: &Arc<IconDir>, icon_name: &str, icon_type: IconFileType) -> Option<IconFile> {
+        let dir_path = self.content_dir.join(icon_dir_info.path());
+
+        match &self.memory_files {
+            Some(memory_files) => {
+                let (_, _, data) = memory_files
+                    .get(&dir_path)?
+                    .iter()
+                    .find(|(name, file_type, _)| name == icon_name && *file_type == icon_type)?;
+
+                Some(IconFile::from_bytes(icon_dir_info.clone(), data.clone(), icon_type))
+            }
+            None => {
+                let path = dir_path.join(icon_name).with_extension(icon_type.as_ref());
+
+                path.exists()
+                    .then(|| IconFile::new(icon_dir_info.clone(), path, icon_type))
+            }
+        }
+    }
+
     pub fn entries(&self, icon_name: &str) -> Vec<IconFile> {
+        self.probe_entries(icon_name, IconFileType::types(), &mut None)
+    }
+
+    /// Like [`IconTheme::entries()`], but only looks for files of the given types, stops once `probes`
+    /// paths were checked and decrements `probes` for every checked path. `None` means unbounded.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "trace",
+            skip_all,
+            fields(theme = %self.content_dir.display(), icon_name = icon_name, candidates)
+        )
+    )]
+    pub(crate) fn probe_entries(
+        &self,
+        icon_name: &str,
+        icon_types: &[IconFileType],
+        probes: &mut Option<usize>,
+    ) -> Vec<IconFile> {
         if icon_name.is_empty() {
             return Vec::new();
         }
 
         let mut entries = Vec::new();
+        let cached_images = self.cached_images(icon_name);
 
         for icon_dir_info in &self.key_list {
-            for icon_type in IconFileType::types() {
-                let icon_path = self
-                    .content_dir
-                    .join(icon_dir_info.path())
-                    .join(icon_name)
-                    .with_extension(icon_type.as_ref());
-
-                if icon_path.exists() {
-                    entries.push(IconFile::new(icon_dir_info.clone(), icon_path, *icon_type));
+            for icon_type in icon_types {
+                // Directories the icon cache does not list the icon in need not be probed.
+                if cached_images.as_ref().is_some_and(|images| {
+                    !images.contains(&(icon_dir_info.path().to_path_buf(), *icon_type))
+                }) {
+                    continue;
+                }
+
+                if !take_probe(probes) {
+                    #[cfg(feature = "tracing")]
+                    tracing::Span::current().record("candidates", entries.len());
+
+                    return entries;
                 }
+
+                entries.extend(self.file_at(icon_dir_info, icon_name, *icon_type));
             }
         }
 
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("candidates", entries.len());
+
         entries
     }
+
+    /// Returns the directories and file types the icon cache of this theme lists `icon_name` in,
+    /// or `None` if there is no usable cache.
+    #[cfg(feature = "gtk-cache")]
+    fn cached_images(&self, icon_name: &str) -> Option<Vec<(PathBuf, IconFileType)>> {
+        self.icon_cache.as_ref()?.lookup(icon_name)
+    }
+
+    #[cfg(not(feature = "gtk-cache"))]
+    fn cached_images(&self, _icon_name: &str) -> Option<Vec<(PathBuf, IconFileType)>> {
+        None
+    }
+
+    /// Returns the icon files whose names match `icon_name` case-insensitively.
+    /// Unlike [`IconTheme::entries()`], this scans every directory of the theme.
+    pub fn entries_case_insensitive(&self, icon_name: &str) -> Vec<IconFile> {
+        self.probe_entries_case_insensitive(icon_name, IconFileType::types(), &mut None)
+    }
+
+    /// Like [`IconTheme::entries_case_insensitive()`], but only returns files of the given types,
+    /// stops once `probes` directories were scanned and decrements `probes` for every scanned directory.
+    /// `None` means unbounded.
+    pub(crate) fn probe_entries_case_insensitive(
+        &self,
+        icon_name: &str,
+        icon_types: &[IconFileType],
+        probes: &mut Option<usize>,
+    ) -> Vec<IconFile> {
+        if icon_name.is_empty() {
+            return Vec::new();
+        }
+
+        let icon_name = icon_name.to_lowercase();
+
+        self.key_list
+            .iter()
+            .take_while(|_| take_probe(probes))
+            .flat_map(|icon_dir_info| {
+                let mut entries: Vec<IconFile> = self
+                    .dir_files(icon_dir_info)
+                    .filter(|(name, file)| {
+                        name.to_lowercase() == icon_name && icon_types.contains(&file.icon_type())
+                    })
+                    .map(|(_, file)| file)
+                    .collect();
+
+                // Keep the order of `entries`, which lists the types of each directory in order.
+                entries.sort_by_key(|file| {
+                    IconFileType::types()
+                        .iter()
+                        .position(|icon_type| *icon_type == file.icon_type())
+                });
+
+                entries
+            })
+            .collect()
+    }
 }
 
+/// All installations of an icon theme in the search paths, together with the names of its parents.
+/// Obtain it with [`IconLoader::theme_chain()`](crate::IconLoader::theme_chain).
 #[derive(Debug)]
 pub struct IconThemeChain {
     pub(crate) name: String,
     pub(crate) themes: Vec<IconTheme>,
     pub(crate) parents: Vec<String>,
-    pub(crate) cache: Arc<ThemeCache>,
+    // A strong reference would form a cycle with the cache, which holds this chain.
+    cache: Weak<ThemeCache>,
+    options: ThemeOptions,
+    misses: DashSet<String>,
 }
 
 impl IconThemeChain {
-    pub(crate) fn find(cache: Arc<ThemeCache>, theme_name: &str, search_paths: &[PathBuf]) -> IconThemeChain {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(theme_name = theme_name, search_paths = search_paths.len(), installations)
+        )
+    )]
+    pub(crate) fn find(cache: &Arc<ThemeCache>, theme_name: &str, search_paths: &[PathBuf]) -> IconThemeChain {
         let mut themes = IconThemeChain {
             name: theme_name.to_string(),
             themes: Vec::new(),
             parents: Vec::new(),
-            cache
+            cache: Arc::downgrade(cache),
+            options: cache.options.clone(),
+            misses: DashSet::new(),
+        };
+
+        let case_insensitive = themes.options.case_insensitive_theme_names;
+        let lenient = themes.options.lenient_parsing;
+        let load = |search_path: &PathBuf| {
+            let mut content_dir = search_path.join(theme_name);
+
+            if !content_dir.is_dir() && case_insensitive {
+                if let Some(dir) = find_dir_case_insensitive(search_path, theme_name) {
+                    content_dir = dir;
+                }
+            }
+
+            let mut parents = Vec::new();
+            let theme = IconTheme::from_dir(content_dir, &mut parents, lenient);
+
+            (theme, parents)
         };
 
-        for search_path in search_paths {
-            let content_dir = search_path.join(theme_name);
+        // The themes are loaded in parallel, but collected in the order of the search paths.
+        #[cfg(feature = "parallel")]
+        let loaded: Vec<_> = search_paths.par_iter().map(load).collect();
+        #[cfg(not(feature = "parallel"))]
+        let loaded: Vec<_> = search_paths.iter().map(load).collect();
 
-            match IconTheme::from_dir(content_dir, &mut themes.parents) {
+        for (theme, parents) in loaded {
+            match theme {
                 Ok(theme) => themes.themes.push(theme),
                 Err(_e) =>
                 {
@@ -123,49 +520,211 @@ impl IconThemeChain {
                     }
                 }
             }
-        }
 
-        let hicolor = String::from("hicolor");
+            for parent in parents {
+                if !themes.parents.contains(&parent) {
+                    themes.parents.push(parent);
+                }
+            }
+        }
 
-        if !themes.parents.contains(&hicolor) {
-            themes.parents.push(hicolor);
+        #[cfg(feature = "embed")]
+        if let Some(dir) = themes.options.embedded.and_then(|root| root.get_dir(theme_name)) {
+            match IconTheme::from_embedded(dir, &mut themes.parents) {
+                Ok(theme) => themes.themes.push(theme),
+                Err(_e) => {
+                    #[cfg(feature = "theme_error_log")]
+                    log::warn!("{}", _e);
+                }
+            }
         }
 
+        themes.add_implicit_parent();
+
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("installations", themes.themes.len());
+
         themes
     }
 
-    pub(crate) fn find_icon(&self, icon_name: &str) -> Option<Icon> {
-        if self.is_empty() {
+    /// Creates a chain that consists of the single theme `theme`.
+    pub(crate) fn from_theme(
+        cache: &Arc<ThemeCache>,
+        theme_name: &str,
+        theme: IconTheme,
+        parents: Vec<String>,
+    ) -> IconThemeChain {
+        let mut themes = IconThemeChain {
+            name: theme_name.to_string(),
+            themes: vec![theme],
+            parents,
+            cache: Arc::downgrade(cache),
+            options: cache.options.clone(),
+            misses: DashSet::new(),
+        };
+
+        themes.add_implicit_parent();
+
+        themes
+    }
+
+    /// Every theme implicitly inherits from the implicit fallback theme, which is `hicolor` by default.
+    fn add_implicit_parent(&mut self) {
+        if let Some(fallback) = &self.options.implicit_fallback_theme {
+            if !self.parents.contains(fallback) {
+                self.parents.push(fallback.clone());
+            }
+        }
+    }
+
+    /// Looks for the icon `icon_name` in all themes of this chain, but checks at most `probes` paths.
+    pub(crate) fn find_icon(&self, icon_name: &str, probes: &mut Option<usize>) -> Option<Icon> {
+        if self.is_empty() || self.misses.contains(icon_name) {
             return None;
         }
 
-        let entries: Vec<IconFile> = self
-            .themes
-            .iter()
-            .flat_map(|theme| theme.entries(icon_name))
-            .collect();
+        let entries = self.probe_entries(icon_name, self.options.allowed_types(), probes);
 
         if entries.is_empty() {
+            // The icon might exist in a path that was not checked.
+            if *probes == Some(0) {
+                return None;
+            }
+
+            // Forget all misses once the limit is reached, so the cache cannot grow unbounded.
+            if self.misses.len() >= MAX_CACHED_MISSES {
+                self.misses.clear();
+            }
+
+            self.misses.insert(icon_name.to_string());
+
             return None;
         }
 
         Icon::new(icon_name.into(), self.name.clone(), entries)
     }
 
+    /// Returns the files of the given types of the icon `icon_name` in all themes of this chain,
+    /// but checks at most `probes` paths.
+    pub(crate) fn probe_entries(
+        &self,
+        icon_name: &str,
+        icon_types: &[IconFileType],
+        probes: &mut Option<usize>,
+    ) -> Vec<IconFile> {
+        let mut entries = Vec::new();
+
+        for theme in &self.themes {
+            let theme_entries = if let Some(resolver) = &self.options.entry_resolver {
+                let mut theme_entries = (resolver.0)(theme, icon_name);
+                theme_entries.retain(|file| icon_types.contains(&file.icon_type()));
+                theme_entries
+            } else if self.options.case_insensitive_icon_names {
+                theme.probe_entries_case_insensitive(icon_name, icon_types, probes)
+            } else {
+                theme.probe_entries(icon_name, icon_types, probes)
+            };
+
+            merge_entries(&mut entries, theme_entries);
+        }
+
+        entries
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.themes.is_empty()
     }
-    
+
+    /// Returns the name of the theme.
     pub fn name(&self) -> &str {
         self.name.as_str()
     }
-    
+
+    /// Returns the installations of the theme, in the order of the search paths.
     pub fn themes(&self) -> &[IconTheme] {
         &self.themes
     }
 
+    /// Returns the names of the parent themes declared by `Inherits`, followed by the implicit
+    /// fallback theme, which is `hicolor` by default.
+    /// Unlike [`IconThemeChain::parents()`], this does not load the parents.
+    pub fn parent_names(&self) -> &[String] {
+        &self.parents
+    }
+
+    /// Returns the chains of the parent themes, which are loaded on demand.
+    /// Yields nothing once the theme cache of the loader this chain came from is dropped.
     pub fn parents(&self) -> impl Iterator<Item = Arc<IconThemeChain>> + use<'_> {
-        self.parents.iter()
-            .map(move |parent| self.cache.theme(parent.as_str()).clone())
+        let cache = self.cache.upgrade();
+
+        self.parents
+            .iter()
+            .filter_map(move |parent| Some(cache.as_ref()?.theme(parent.as_str())))
+    }
+}
+
+/// Parses the `index.theme` file at `path` with the contents `index`, dropping every line
+/// the parser rejects, so that themes with minor corruption can still be used.
+fn parse_lenient(index: &str, _path: &Path) -> std::result::Result<ini::Ini, ini::Error> {
+    let mut lines: Vec<&str> = index.lines().collect();
+
+    loop {
+        match ini::Ini::load_from_str(&lines.join("\n")) {
+            Ok(ini) => return Ok(ini),
+            // Every iteration removes a line, so this terminates.
+            Err(e) if (1..=lines.len()).contains(&e.line) => {
+                #[cfg(feature = "theme_error_log")]
+                log::warn!("Skipping line {} of {}: {}", e.line, _path.display(), e.msg);
+
+                lines.remove(e.line - 1);
+            }
+            Err(e) => return Err(ini::Error::Parse(e)),
+        }
+    }
+}
+
+/// Appends the entries of a theme to the entries of the same theme in earlier search paths.
+/// Earlier search paths take precedence, so entries of the same type in the same directory
+/// as an already present entry are dropped.
+pub(crate) fn merge_entries(entries: &mut Vec<IconFile>, theme_entries: Vec<IconFile>) {
+    let present: Vec<_> = entries.iter().map(IconFile::dedup_key).collect();
+
+    entries.extend(
+        theme_entries
+            .into_iter()
+            .filter(|file| !present.contains(&file.dedup_key())),
+    );
+}
+
+/// Returns the icon name and type of the icon file at `path`, if it has a known extension.
+fn icon_name_and_type(path: &Path) -> Option<(String, IconFileType)> {
+    let icon_type = IconFileType::from_path(path)?;
+    let name = path.file_stem()?.to_str()?.to_string();
+
+    Some((name, icon_type))
+}
+
+/// Consumes one of the remaining `probes` and returns whether one was left.
+fn take_probe(probes: &mut Option<usize>) -> bool {
+    match probes {
+        Some(0) => false,
+        Some(remaining) => {
+            *remaining -= 1;
+            true
+        }
+        None => true,
     }
 }
+
+/// Returns the first directory in `parent` whose name matches `name` case-insensitively.
+fn find_dir_case_insensitive(parent: &Path, name: &str) -> Option<PathBuf> {
+    let name = name.to_lowercase();
+
+    fs::read_dir(parent)
+        .ok()?
+        .flatten()
+        .find(|entry| {
+            entry.file_name().to_string_lossy().to_lowercase() == name && entry.path().is_dir()
+        })
+        .map(|entry| entry.path())
+}
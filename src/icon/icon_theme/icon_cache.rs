@@ -0,0 +1,151 @@
+use crate::icon::IconFileType;
+
+use std::{
+    convert::TryFrom,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// The file name of the cache GTK generates for icon themes with `gtk-update-icon-cache`.
+const CACHE_FILE_NAME: &str = "icon-theme.cache";
+
+/// Marks the end of a hash bucket's chain of icons.
+const CHAIN_END: usize = 0xffff_ffff;
+
+/// The flags of an image telling which file types exist.
+const HAS_SUFFIX_XPM: u16 = 1;
+const HAS_SUFFIX_SVG: u16 = 2;
+const HAS_SUFFIX_PNG: u16 = 4;
+
+/// The `icon-theme.cache` file of a theme, which maps icon names to the directories containing them.
+/// The file is read into memory once, since mapping it would require unsafe code.
+pub(crate) struct IconCache {
+    data: Vec<u8>,
+}
+
+impl IconCache {
+    /// Reads the cache of the theme in `content_dir` with the icon directories `dirs`.
+    /// Returns `None` if there is no cache, it is older than the theme directory or one of
+    /// the icon directories or its version is not supported.
+    pub(crate) fn load<'a>(
+        content_dir: &Path,
+        dirs: impl IntoIterator<Item = &'a Path>,
+    ) -> Option<Self> {
+        let cache_path = content_dir.join(CACHE_FILE_NAME);
+        let cache_modified = fs::metadata(&cache_path).ok()?.modified().ok()?;
+        let modified = |path: &Path| fs::metadata(path).and_then(|metadata| metadata.modified());
+
+        // Unlike GTK, which only checks the theme directory, also check the icon directories,
+        // since adding an icon file does not change the modification time of the theme directory.
+        let is_outdated = std::iter::once(content_dir.to_path_buf())
+            .chain(dirs.into_iter().map(|dir| content_dir.join(dir)))
+            .filter_map(|dir| modified(&dir).ok())
+            .any(|dir_modified| dir_modified > cache_modified);
+
+        if is_outdated {
+            #[cfg(feature = "theme_error_log")]
+            log::debug!("Ignoring outdated icon cache {}", cache_path.display());
+
+            return None;
+        }
+
+        let cache = Self {
+            data: fs::read(cache_path).ok()?,
+        };
+
+        (cache.read_u16(0)? == 1).then_some(cache)
+    }
+
+    /// Returns the directories and file types the icon `icon_name` exists in.
+    /// Returns `None` if the cache is malformed.
+    pub(crate) fn lookup(&self, icon_name: &str) -> Option<Vec<(PathBuf, IconFileType)>> {
+        let hash_offset = self.read_u32(4)?;
+        let directory_list_offset = self.read_u32(8)?;
+        let bucket_count = self.read_u32(hash_offset)?;
+
+        if bucket_count == 0 {
+            return Some(Vec::new());
+        }
+
+        let bucket = icon_name_hash(icon_name) as usize % bucket_count;
+        let mut icon_offset = self.read_u32(hash_offset + 4 + 4 * bucket)?;
+
+        // Every icon is visited at most once, unless the chain is cyclic.
+        for _ in 0..self.data.len() {
+            if icon_offset == CHAIN_END {
+                break;
+            }
+
+            if self.read_str(self.read_u32(icon_offset + 4)?)? == icon_name {
+                return self.images(self.read_u32(icon_offset + 8)?, directory_list_offset);
+            }
+
+            icon_offset = self.read_u32(icon_offset)?;
+        }
+
+        Some(Vec::new())
+    }
+
+    fn images(
+        &self,
+        image_list_offset: usize,
+        directory_list_offset: usize,
+    ) -> Option<Vec<(PathBuf, IconFileType)>> {
+        let mut images = Vec::new();
+
+        for index in 0..self.read_u32(image_list_offset)? {
+            let image_offset = image_list_offset + 4 + 8 * index;
+            let directory_index = usize::from(self.read_u16(image_offset)?);
+            let flags = self.read_u16(image_offset + 2)?;
+            let directory = self.read_u32(directory_list_offset + 4 + 4 * directory_index)?;
+            let directory = PathBuf::from(self.read_str(directory)?);
+
+            for (flag, icon_type) in [
+                (HAS_SUFFIX_PNG, IconFileType::PNG),
+                (HAS_SUFFIX_SVG, IconFileType::SVG),
+                (HAS_SUFFIX_XPM, IconFileType::XPM),
+            ] {
+                if flags & flag != 0 {
+                    images.push((directory.clone(), icon_type));
+                }
+            }
+        }
+
+        Some(images)
+    }
+
+    fn read_u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset.checked_add(2)?)?;
+
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn read_u32(&self, offset: usize) -> Option<usize> {
+        let bytes = self.data.get(offset..offset.checked_add(4)?)?;
+
+        usize::try_from(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).ok()
+    }
+
+    fn read_str(&self, offset: usize) -> Option<&str> {
+        let bytes = self.data.get(offset..)?;
+        let end = bytes.iter().position(|byte| *byte == 0)?;
+
+        std::str::from_utf8(&bytes[..end]).ok()
+    }
+}
+
+impl fmt::Debug for IconCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "IconCache({} bytes)", self.data.len())
+    }
+}
+
+/// The hash function GTK uses for icon names, which treats bytes as signed.
+pub(crate) fn icon_name_hash(icon_name: &str) -> u32 {
+    let mut bytes = icon_name.bytes().map(|byte| byte as i8 as u32);
+    let first = bytes.next().unwrap_or(0);
+
+    bytes.fold(first, |hash, byte| {
+        (hash << 5).wrapping_sub(hash).wrapping_add(byte)
+    })
+}
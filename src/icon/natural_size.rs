@@ -0,0 +1,71 @@
+use super::{IconFile, IconFileType};
+
+use std::io::Cursor;
+
+impl IconFile {
+    /// Returns the actual pixel dimensions of this icon file, which may differ from the size
+    /// declared by its directory. Only the header of PNG files is decoded. For SVG files the
+    /// `width` and `height` attributes are used, or the `viewBox` if they are missing.
+    /// Returns `None` if the dimensions cannot be determined, e.g. for XPM files.
+    pub fn natural_size(&self) -> Option<(u32, u32)> {
+        match self.icon_type() {
+            IconFileType::PNG => image::ImageReader::new(Cursor::new(self.read().ok()?))
+                .with_guessed_format()
+                .ok()?
+                .into_dimensions()
+                .ok(),
+            IconFileType::SVG => svg_size(&String::from_utf8(self.read().ok()?).ok()?),
+            IconFileType::XPM => None,
+        }
+    }
+}
+
+/// Reads the size of an SVG document from the attributes of its root element.
+fn svg_size(svg: &str) -> Option<(u32, u32)> {
+    let tag = &svg[svg.find("<svg")?..];
+    let tag = &tag[..tag.find('>')?];
+
+    let width = attribute(tag, "width").and_then(parse_length);
+    let height = attribute(tag, "height").and_then(parse_length);
+
+    if let (Some(width), Some(height)) = (width, height) {
+        return Some((width, height));
+    }
+
+    let view_box: Vec<&str> = attribute(tag, "viewBox")?
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|value| !value.is_empty())
+        .collect();
+
+    match view_box.as_slice() {
+        [_, _, width, height] => Some((parse_length(width)?, parse_length(height)?)),
+        _ => None,
+    }
+}
+
+/// Returns the value of the attribute `name` of an XML start tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    tag.match_indices(name).find_map(|(index, _)| {
+        if !tag[..index].ends_with(char::is_whitespace) {
+            return None;
+        }
+
+        let rest = tag[index + name.len()..].trim_start().strip_prefix('=')?.trim_start();
+        let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let value = &rest[1..];
+
+        Some(&value[..value.find(quote)?])
+    })
+}
+
+/// Parses a length in pixels, like `16` or `16px`. Relative lengths are not supported.
+fn parse_length(length: &str) -> Option<u32> {
+    let length = length.trim();
+    let length: f64 = length.strip_suffix("px").unwrap_or(length).parse().ok()?;
+
+    if length > 0.0 && length <= f64::from(u32::MAX) {
+        Some(length.round() as u32)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,88 @@
+use super::{IconFile, IconFileType};
+use crate::error::{Error, Result};
+
+use std::{error::Error as StdError, path::Path};
+
+use image::RgbaImage;
+use resvg::{tiny_skia, usvg};
+
+/// The placeholder color symbolic icons use for the foreground.
+const PLACEHOLDER_COLOR: &str = "#bebebe";
+
+impl IconFile {
+    /// Renders this symbolic icon into a `size` x `size` image, replacing the placeholder
+    /// foreground color `#bebebe` with `fg`, given as RGBA.
+    /// Returns [`Error::NotSymbolic`] if this is no SVG file whose name ends in `-symbolic`.
+    /// SVG files held in memory have no name and are always rendered.
+    pub fn render_symbolic(&self, size: u16, fg: [u8; 4]) -> Result<RgbaImage> {
+        let is_symbolic = self.icon_type() == IconFileType::SVG
+            && self.path().is_none_or(|path| {
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| stem.ends_with("-symbolic"))
+            });
+
+        if !is_symbolic {
+            return Err(Error::NotSymbolic {
+                path: self.path().map(Path::to_path_buf),
+            });
+        }
+
+        self.render_recolored(size, fg).map_err(|source| Error::Render {
+            path: self.path().map(Path::to_path_buf),
+            source,
+        })
+    }
+
+    fn render_recolored(
+        &self,
+        size: u16,
+        fg: [u8; 4],
+    ) -> std::result::Result<RgbaImage, Box<dyn StdError + Send + Sync>> {
+        let [r, g, b, a] = fg;
+        let color = format!("rgba({},{},{},{})", r, g, b, f32::from(a) / 255.0);
+        let svg = replace_ignore_ascii_case(&String::from_utf8(self.read()?)?, PLACEHOLDER_COLOR, &color);
+
+        let tree = usvg::Tree::from_str(&svg, &usvg::Options::default())?;
+        let mut pixmap = tiny_skia::Pixmap::new(u32::from(size), u32::from(size))
+            .ok_or("The size must not be zero")?;
+
+        let tree_size = tree.size();
+        let scale = f32::from(size) / tree_size.width().max(tree_size.height());
+        resvg::render(
+            &tree,
+            tiny_skia::Transform::from_scale(scale, scale),
+            &mut pixmap.as_mut(),
+        );
+
+        let pixels = pixmap
+            .pixels()
+            .iter()
+            .flat_map(|pixel| {
+                let pixel = pixel.demultiply();
+                [pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]
+            })
+            .collect();
+
+        // The buffer has exactly `size * size` pixels.
+        Ok(RgbaImage::from_raw(u32::from(size), u32::from(size), pixels).unwrap())
+    }
+}
+
+/// Replaces all occurrences of the ASCII string `from` in `string`, ignoring ASCII case.
+fn replace_ignore_ascii_case(string: &str, from: &str, to: &str) -> String {
+    // Lowercasing ASCII characters keeps all byte offsets intact.
+    let lowercase = string.to_ascii_lowercase();
+    let mut result = String::with_capacity(string.len());
+    let mut last_end = 0;
+
+    for (index, _) in lowercase.match_indices(from) {
+        result.push_str(&string[last_end..index]);
+        result.push_str(to);
+        last_end = index + from.len();
+    }
+
+    result.push_str(&string[last_end..]);
+
+    result
+}
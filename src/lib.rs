@@ -47,14 +47,25 @@ mod error;
 mod icon;
 mod loader;
 mod search_paths;
+mod snapshot;
 mod theme_name_provider;
+#[cfg(feature = "watch")]
+mod watch;
+
+#[cfg(test)]
+#[allow(clippy::module_inception)]
 mod test;
 
 pub use error::{Error, ProviderError, Result};
-pub use icon::{Icon, IconDir, IconFile, IconFileType, IconSizeType};
+pub use icon::{
+    FileQuery, Icon, IconDir, IconFile, IconFileType, IconSizeType, IconTheme, IconThemeChain,
+};
 pub use loader::*;
 pub use search_paths::SearchPaths;
+pub use snapshot::ThemeSnapshot;
 pub use theme_name_provider::ThemeNameProvider;
+#[cfg(feature = "watch")]
+pub use watch::WatchGuard;
 
 use std::sync::OnceLock;
 
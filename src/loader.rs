@@ -1,57 +1,321 @@
+mod builder;
+
+pub use builder::IconLoaderBuilder;
+
 use crate::{
-    error::Result,
-    icon::{Icon, IconThemeChain},
+    error::{Error, Result},
+    icon::{
+        icon_theme::{self, IconTheme},
+        Icon, IconDir, IconFile, IconFileType, IconThemeChain,
+    },
     search_paths::SearchPaths,
+    snapshot::ThemeSnapshot,
     theme_name_provider::ThemeNameProvider,
 };
 use dashmap::DashMap;
-use std::collections::VecDeque;
-use std::sync::Arc;
-use std::{borrow::Cow, path::PathBuf};
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
+#[cfg(feature = "zip")]
+use std::io::{Read, Seek};
+
+/// The name of the pseudo theme of icons found in the pixmaps directory.
+const PIXMAPS_THEME_NAME: &str = "pixmaps";
+
+/// The name of the pseudo theme of icons loaded from absolute paths.
+const FILE_THEME_NAME: &str = "file";
+
+/// A callback that is invoked with the icon name and the name of the theme it was found in.
+pub type ResolutionObserver = Box<dyn Fn(&str, &str) + Send + Sync>;
+
+/// A function that returns the files of the icon with the given name in an icon theme.
+/// See [`IconLoader::set_entry_resolver()`].
+pub type EntryResolver = Box<dyn Fn(&IconTheme, &str) -> Vec<IconFile> + Send + Sync>;
+
+/// An [`EntryResolver`] shared by all copies of the theme options.
+#[derive(Clone)]
+pub(crate) struct SharedEntryResolver(pub(crate) Arc<EntryResolver>);
+
+impl std::fmt::Debug for SharedEntryResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EntryResolver")
+    }
+}
+
+/// Determines what [`IconLoader::update_theme_name()`] does if the provided theme does not exist.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ThemeNotFoundPolicy {
+    /// Return [`Error::ThemeNotFound`](crate::Error::ThemeNotFound) and keep the current theme name.
+    #[default]
+    Error,
+
+    /// Use the `hicolor` base theme instead.
+    FallbackToBase,
+}
+
+/// Determines the order in which the ancestors of a theme are searched for an icon.
+///
+/// In both orders the theme itself is searched first and every theme is searched at most once.
+/// The implicit fallback theme, `hicolor` by default, is searched after all other ancestors,
+/// as the freedesktop icon theme specification requires.
+/// The fallback theme of the loader and its ancestors are searched afterwards in the same order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TraversalOrder {
+    /// Search each parent and all of its ancestors before the next parent declared by `Inherits`,
+    /// like the `FindIconHelper` recursion of the specification's reference implementation.
+    #[default]
+    DepthFirst,
+
+    /// Search all parents declared by `Inherits` before any of their ancestors, level by level.
+    BreadthFirst,
+}
 
 /// The central icon loader struct.
 ///
 /// It lets you load named theme icons from system themes as well as custom themes.
-#[derive(Debug)]
 pub struct IconLoader {
     theme_name: String,
     fallback_theme_name: String,
+    default_theme_name: Option<String>,
+    theme_name_provider: ThemeNameProvider,
+    theme_not_found_policy: ThemeNotFoundPolicy,
+    traversal_order: TraversalOrder,
+    pixmaps_fallback: bool,
+    pixmaps_path: PathBuf,
+    max_probes_per_lookup: Option<usize>,
+    context_priority: Vec<String>,
+    fallback_icon_name: Option<String>,
     theme_cache: Arc<ThemeCache>,
+    #[cfg(feature = "watch")]
+    pub(crate) watched_cache: crate::watch::CacheSlot,
+    preloaded_icons: Option<DashMap<String, Icon>>,
+    resolution_observer: Option<ResolutionObserver>,
 }
 
 #[derive(Debug, Default)]
 pub struct ThemeCache {
-    cache: DashMap<String, Arc<IconThemeChain>>,
+    pub(crate) cache: DashMap<String, CachedTheme>,
     search_paths: SearchPaths,
+    pub(crate) options: ThemeOptions,
+    current_theme: RwLock<String>,
+}
+
+/// A cached theme chain together with the time it was last accessed.
+#[derive(Debug)]
+pub(crate) struct CachedTheme {
+    chain: Arc<IconThemeChain>,
+    last_access: Mutex<Instant>,
+}
+
+impl CachedTheme {
+    fn new(chain: IconThemeChain) -> Self {
+        CachedTheme {
+            chain: Arc::new(chain),
+            last_access: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_access.lock().unwrap() = Instant::now();
+    }
+
+    fn last_access(&self) -> Instant {
+        *self.last_access.lock().unwrap()
+    }
+
+    fn idle_time(&self) -> Duration {
+        self.last_access().elapsed()
+    }
+}
+
+/// Options that change how icon themes are discovered on disk and cached.
+#[derive(Clone, Debug)]
+pub(crate) struct ThemeOptions {
+    pub(crate) case_insensitive_theme_names: bool,
+    pub(crate) case_insensitive_icon_names: bool,
+    pub(crate) capacity: Option<usize>,
+    pub(crate) allowed_types: Option<Vec<IconFileType>>,
+    pub(crate) implicit_fallback_theme: Option<String>,
+    pub(crate) lenient_parsing: bool,
+    pub(crate) entry_resolver: Option<SharedEntryResolver>,
+    #[cfg(feature = "embed")]
+    pub(crate) embedded: Option<&'static include_dir::Dir<'static>>,
+}
+
+impl Default for ThemeOptions {
+    fn default() -> Self {
+        ThemeOptions {
+            case_insensitive_theme_names: false,
+            case_insensitive_icon_names: false,
+            capacity: None,
+            allowed_types: None,
+            implicit_fallback_theme: Some(String::from("hicolor")),
+            lenient_parsing: false,
+            entry_resolver: None,
+            #[cfg(feature = "embed")]
+            embedded: None,
+        }
+    }
+}
+
+impl ThemeOptions {
+    /// Returns the file types that are looked for, which are all types unless restricted.
+    pub(crate) fn allowed_types(&self) -> &[IconFileType] {
+        self.allowed_types.as_deref().unwrap_or(IconFileType::types())
+    }
 }
 
 impl ThemeCache {
+    fn new(search_paths: SearchPaths, options: ThemeOptions) -> Self {
+        ThemeCache {
+            cache: DashMap::new(),
+            search_paths,
+            options,
+            current_theme: Default::default(),
+        }
+    }
+
+    /// Sets the theme of the loader, which is never evicted together with its parents.
+    fn set_current_theme(&self, theme_name: &str) {
+        *self.current_theme.write().unwrap() = theme_name.into();
+    }
+
     /// Returns the paths that are searched for icon themes.
-    pub fn search_paths(&self) -> Cow<[PathBuf]> {
+    pub fn search_paths(&self) -> Cow<'_, [PathBuf]> {
         self.search_paths.paths()
     }
 
     pub fn theme<'a>(self: &'a Arc<Self>, theme_name: &'a str) -> Arc<IconThemeChain> {
-        if !self.cache.contains_key(theme_name) {
-            let new_themes = IconThemeChain::find(self.clone(), theme_name, &self.search_paths());
+        if let Some(cached_theme) = self.cache.get(theme_name) {
+            cached_theme.touch();
+
+            return cached_theme.chain.clone();
+        }
+
+        let new_themes = IconThemeChain::find(self, theme_name, &self.search_paths());
+        let cached_theme = CachedTheme::new(new_themes);
+        let chain = cached_theme.chain.clone();
 
-            self.cache.insert(theme_name.into(), Arc::new(new_themes));
+        self.cache.insert(theme_name.into(), cached_theme);
+        self.evict_least_recently_used(theme_name);
+
+        chain
+    }
+
+    /// Removes the least recently used themes until the cache does not exceed its capacity.
+    /// `theme_name`, the current theme and the cached parents of both are kept.
+    fn evict_least_recently_used(&self, theme_name: &str) {
+        let capacity = match self.options.capacity {
+            Some(capacity) => capacity,
+            None => return,
+        };
+
+        if self.cache.len() <= capacity {
+            return;
+        }
+
+        let mut kept = HashSet::new();
+        let mut pending = vec![
+            theme_name.to_string(),
+            self.current_theme.read().unwrap().clone(),
+        ];
+
+        while let Some(name) = pending.pop() {
+            if kept.contains(&name) {
+                continue;
+            }
+
+            if let Some(cached_theme) = self.cache.get(&name) {
+                pending.extend(cached_theme.chain.parents.iter().cloned());
+            }
+
+            kept.insert(name);
         }
 
-        // Unwrapping is ok, since we just added a value
-        self.cache.get(theme_name).unwrap().clone()
+        while self.cache.len() > capacity {
+            let least_recently_used = self
+                .cache
+                .iter()
+                .filter(|entry| !kept.contains(entry.key()))
+                .min_by_key(|entry| entry.value().last_access())
+                .map(|entry| entry.key().clone());
+
+            match least_recently_used {
+                Some(least_recently_used) => self.cache.remove(&least_recently_used),
+                None => break,
+            };
+        }
+    }
+
+    /// Loads a theme with `load`, which receives the list to add the theme's parents to,
+    /// and caches it under `theme_name`.
+    fn insert_virtual(
+        self: &Arc<Self>,
+        theme_name: &str,
+        load: impl FnOnce(&mut Vec<String>) -> icon_theme::Result<IconTheme>,
+    ) -> Result<()> {
+        let mut parents = Vec::new();
+        let theme = load(&mut parents).map_err(|source| Error::InvalidTheme {
+            theme_name: theme_name.into(),
+            source: source.into(),
+        })?;
+        let chain = IconThemeChain::from_theme(self, theme_name, theme, parents);
+
+        self.cache.insert(theme_name.into(), CachedTheme::new(chain));
+        self.evict_least_recently_used(theme_name);
+
+        Ok(())
+    }
+
+    /// Removes all themes from the cache that were not accessed within `max_idle`.
+    pub fn trim(&self, max_idle: Duration) {
+        self.cache
+            .retain(|_, cached_theme| cached_theme.idle_time() <= max_idle);
+    }
+
+    /// Removes the theme with the name `theme_name` from the cache.
+    #[cfg(feature = "watch")]
+    pub(crate) fn evict(&self, theme_name: &str) {
+        self.cache.remove(theme_name);
     }
 }
 
 impl IconLoader {
     pub fn new(theme_name: impl Into<String>, fallback_theme_name: impl Into<String>) -> Self {
+        let theme_name = theme_name.into();
+        let theme_cache = Arc::new(ThemeCache::default());
+        theme_cache.set_current_theme(&theme_name);
+
         IconLoader {
-            theme_name: theme_name.into(),
+            theme_name_provider: ThemeNameProvider::user(theme_name.clone()),
+            theme_name,
             fallback_theme_name: fallback_theme_name.into(),
-            theme_cache: Default::default(),
+            default_theme_name: None,
+            theme_not_found_policy: Default::default(),
+            traversal_order: Default::default(),
+            pixmaps_fallback: false,
+            pixmaps_path: PathBuf::from("/usr/share/pixmaps"),
+            max_probes_per_lookup: None,
+            context_priority: Vec::new(),
+            fallback_icon_name: None,
+            theme_cache,
+            #[cfg(feature = "watch")]
+            watched_cache: Default::default(),
+            preloaded_icons: None,
+            resolution_observer: None,
         }
     }
 
+    /// Returns a builder to configure a new `IconLoader`, see [`IconLoaderBuilder`].
+    pub fn builder() -> IconLoaderBuilder {
+        IconLoaderBuilder::new()
+    }
+
     /// Creates a new `IconLoader` with default settings.
     pub fn new_hicolor() -> Self {
         Self::new("hicolor", "hicolor")
@@ -60,7 +324,36 @@ impl IconLoader {
     pub fn new_from_provider(theme_name_provider: ThemeNameProvider) -> Result<Self> {
         let theme_name = theme_name_provider.theme_name()?;
 
-        Ok(Self::new(&theme_name, &theme_name))
+        let mut loader = Self::new(&theme_name, &theme_name);
+        loader.theme_name_provider = theme_name_provider;
+
+        Ok(loader)
+    }
+
+    /// Creates a new `IconLoader` that loads icon themes from a directory compiled into the binary,
+    /// instead of searching the filesystem. Every subdirectory of `dir` containing an `index.theme`
+    /// file is an icon theme. The icon files of these themes provide their contents via
+    /// [`IconFile::read()`](crate::IconFile::read) and have no path.
+    /// The theme name and the fallback theme name are `hicolor`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// use icon_loader::IconLoader;
+    ///
+    /// static ICONS: include_dir::Dir = include_dir::include_dir!("$CARGO_MANIFEST_DIR/icons");
+    ///
+    /// let mut loader = IconLoader::from_embedded(&ICONS);
+    /// loader.set_theme_name_provider("my-theme");
+    /// loader.update_theme_name().unwrap();
+    /// ```
+    #[cfg(feature = "embed")]
+    pub fn from_embedded(dir: &'static include_dir::Dir<'static>) -> Self {
+        let mut loader = Self::new_hicolor();
+        loader.set_search_paths(SearchPaths::custom(Vec::<PathBuf>::new()));
+        loader.update_theme_options(|options| options.embedded = Some(dir));
+
+        loader
     }
 
     /// Creates a new KDE `IconLoader`.
@@ -80,10 +373,343 @@ impl IconLoader {
     /// Loads the icon with the name `icon_name` from the current icon theme.
     /// If the icon cannot be found, it will be looked for in the fallback icon theme.
     /// If it cannot be found in the fallback theme, `None` is returned.
+    ///
+    /// See also [`IconLoader::try_load_icon()`].
     pub fn load_icon(&self, icon_name: impl AsRef<str>) -> Option<Icon> {
-        self.find_icon(self.theme_name(), icon_name.as_ref())
+        self.try_load_icon(icon_name).ok()
+    }
+
+    /// Loads the icon with the name `icon_name` like [`IconLoader::load_icon()`].
+    /// If it cannot be found, [`Error::ThemeEmpty`](crate::Error::ThemeEmpty) is returned
+    /// if the current theme could not be loaded at all and
+    /// [`Error::IconNotFound`](crate::Error::IconNotFound) otherwise.
+    pub fn try_load_icon(&self, icon_name: impl AsRef<str>) -> Result<Icon> {
+        let icon_name = icon_name.as_ref();
+
+        self.find_icon(self.theme_name(), icon_name).ok_or_else(|| {
+            if self.theme_exists(self.theme_name()) {
+                Error::icon_not_found(icon_name)
+            } else {
+                Error::theme_empty(self.theme_name())
+            }
+        })
+    }
+
+    /// Loads the icon with the name `icon_name` from the theme currently given by the `ThemeNameProvider`,
+    /// which is queried first, but without changing the theme name of the loader.
+    /// Unlike [`IconLoader::load_icon()`], this returns an error if the provider fails or the provided theme
+    /// does not exist according to the [`ThemeNotFoundPolicy`], so a broken theme config can be told apart
+    /// from an icon that does not exist, in which case `Ok(None)` is returned.
+    pub fn load_icon_checked(&self, icon_name: impl AsRef<str>) -> Result<Option<Icon>> {
+        let theme_name = self.provided_theme_name()?;
+
+        Ok(self.find_icon(&theme_name, icon_name.as_ref()))
+    }
+
+    /// Loads the icon with the name `icon_name` like [`IconLoader::load_icon()`] and returns whether
+    /// it was provided by a fallback, i.e. neither by the current theme nor by one of its ancestors.
+    /// [`Icon::theme_name()`] tells the theme the icon was found in.
+    pub fn load_icon_with_origin(&self, icon_name: impl AsRef<str>) -> Option<(Icon, bool)> {
+        let icon = self.load_icon(icon_name)?;
+        let from_fallback = !self
+            .search_order(self.theme_name(), self.theme_name())
+            .iter()
+            .any(|theme_name| theme_name == icon.theme_name());
+
+        Some((icon, from_fallback))
+    }
+
+    /// Returns `true` if the icons named `a` and `b` both exist and resolve to the same file on disk
+    /// at the given size, e.g. because one is a symlink to the other.
+    /// The files are chosen by [`Icon::file_for_size()`] and compared by [`IconFile::canonical_path()`],
+    /// so icons held in memory never compare equal.
+    pub fn same_icon(&self, a: impl AsRef<str>, b: impl AsRef<str>, size: u16) -> bool {
+        let canonical_path = |icon_name: &str| {
+            let icon = self.load_icon(icon_name)?;
+            icon.file_for_size(size).canonical_path().ok()
+        };
+
+        match (canonical_path(a.as_ref()), canonical_path(b.as_ref())) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Loads the icon with the name `icon_name` like [`IconLoader::load_icon()`] and also returns the
+    /// file types the icon exists in, but which were skipped because they are not allowed.
+    /// Use this to tell users which icons have to be converted to a supported format.
+    ///
+    /// See [`IconLoader::set_allowed_file_types()`] for how to restrict the allowed file types.
+    pub fn load_icon_reporting_skipped(
+        &self,
+        icon_name: impl AsRef<str>,
+    ) -> (Option<Icon>, Vec<IconFileType>) {
+        let icon_name = icon_name.as_ref();
+        let allowed_types = self.theme_cache.options.allowed_types();
+        let skipped_types: Vec<_> = IconFileType::types()
+            .iter()
+            .copied()
+            .filter(|icon_type| !allowed_types.contains(icon_type))
+            .collect();
+
+        let mut skipped = Vec::new();
+
+        if !skipped_types.is_empty() && !icon_name.is_empty() {
+            for theme_name in self.search_order(self.theme_name(), &self.fallback_theme_name) {
+                for file in self
+                    .theme_cache
+                    .theme(&theme_name)
+                    .probe_entries(icon_name, &skipped_types, &mut None)
+                {
+                    if !skipped.contains(&file.icon_type()) {
+                        skipped.push(file.icon_type());
+                    }
+                }
+            }
+        }
+
+        (self.load_icon(icon_name), skipped)
+    }
+
+    /// Loads the icon given by the `Icon` key of a `.desktop` file, which is either an icon name
+    /// or the absolute path of an icon file. An existing icon file with a known type is wrapped in an
+    /// icon with the theme name `file` and a single [`IconFile`] with a size of 0.
+    /// Any other value is loaded like in [`IconLoader::load_icon()`].
+    pub fn load_icon_path(&self, value: &str) -> Option<Icon> {
+        let path = Path::new(value);
+
+        if path.is_absolute() && path.is_file() {
+            if let (Some(icon_name), Some(icon_type)) = (
+                path.file_stem().and_then(|stem| stem.to_str()),
+                IconFileType::from_path(path),
+            ) {
+                return self.found(flat_icon(icon_name, FILE_THEME_NAME, path.into(), icon_type));
+            }
+        }
+
+        self.load_icon(value)
+    }
+
+    /// Loads the icon with the name `icon_name` from the theme `theme_name` instead of the current theme.
+    /// The theme's ancestors and the fallback theme are searched like in [`IconLoader::load_icon()`].
+    pub fn load_icon_from_theme(&self, theme_name: &str, icon_name: &str) -> Option<Icon> {
+        self.find_icon(theme_name, icon_name)
+    }
+
+    /// Loads the icon with the name `icon_name` from the ancestors of the theme `theme_name`,
+    /// skipping the theme's own directories. The fallback theme is not searched.
+    /// Comparing the result with [`IconLoader::load_icon_from_theme()`] tells whether a theme
+    /// overrides an icon or inherits it.
+    pub fn load_icon_from_parents(&self, theme_name: &str, icon_name: &str) -> Option<Icon> {
+        if theme_name.is_empty() || icon_name.is_empty() {
+            return None;
+        }
+
+        let mut probes = self.max_probes_per_lookup;
+        let icon = self.search_themes(
+            theme_name,
+            theme_name,
+            |theme_name| self.theme_cache.theme(theme_name).parents.clone(),
+            |name| {
+                if name == theme_name {
+                    None
+                } else {
+                    self.theme_cache
+                        .theme(name)
+                        .find_icon(icon_name, &mut probes)
+                }
+            },
+        );
+
+        self.found(icon)
+    }
+
+    /// Adds the theme `theme_name`, whose `index.theme` file has the contents `index_contents`
+    /// and whose icon directories live in `content_dir`. It replaces any theme of the same name
+    /// and can be used like an installed theme, e.g. as the current theme or as a parent theme.
+    /// Virtual themes are kept in the theme cache, so they are removed whenever it is cleared or trimmed,
+    /// e.g. by [`IconLoader::clear_cache()`] or [`IconLoader::set_search_paths()`].
+    pub fn add_virtual_theme(
+        &self,
+        theme_name: &str,
+        index_contents: &str,
+        content_dir: PathBuf,
+    ) -> Result<()> {
+        self.theme_cache.insert_virtual(theme_name, |parents| {
+            IconTheme::from_index(content_dir, index_contents, parents)
+        })?;
+        self.clear_preloaded_icons();
+
+        Ok(())
+    }
+
+    /// Adds the theme `theme_name` from the zip archive `archive` like [`IconLoader::add_virtual_theme()`].
+    /// The `index.theme` file may live at the root of the archive or in a top level directory.
+    /// The icon files of the theme are read into memory, so they have no path and provide their
+    /// contents via [`IconFile::read()`](crate::IconFile::read).
+    ///
+    /// Returns [`Error::Archive`] if the archive cannot be read and
+    /// [`Error::InvalidTheme`] if it contains no valid theme.
+    #[cfg(feature = "zip")]
+    pub fn add_zip_theme(&self, theme_name: &str, archive: impl Read + Seek) -> Result<()> {
+        let mut archive = zip::ZipArchive::new(archive)?;
+        let mut files = Vec::new();
+
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+
+            if let (true, Some(path)) = (file.is_file(), file.enclosed_name()) {
+                let mut data = Vec::new();
+                file.read_to_end(&mut data)
+                    .map_err(zip::result::ZipError::Io)?;
+                files.push((path, Arc::from(data)));
+            }
+        }
+
+        // The theme lives in the directory of the top-most `index.theme` file.
+        let content_dir = files
+            .iter()
+            .map(|(path, _)| path)
+            .filter(|path| path.file_name() == Some("index.theme".as_ref()))
+            .min_by_key(|path| path.components().count())
+            .and_then(|path| path.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        self.theme_cache.insert_virtual(theme_name, |parents| {
+            IconTheme::from_memory(content_dir, files, parents)
+        })?;
+        self.clear_preloaded_icons();
+
+        Ok(())
+    }
+
+    /// Loads the icon with the name `icon_name` like [`IconLoader::load_icon()`], but runs the
+    /// blocking filesystem work on tokio's blocking thread pool via `spawn_blocking`.
+    /// Must be called from within a tokio runtime.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use icon_loader::IconLoader;
+    ///
+    /// # async fn example() {
+    /// let loader = Arc::new(IconLoader::new_hicolor());
+    ///
+    /// if let Some(icon) = loader.load_icon_async("audio-headphones").await {
+    ///     let path = icon.file_for_size(64).path();
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub async fn load_icon_async(self: &Arc<Self>, icon_name: impl AsRef<str>) -> Option<Icon> {
+        let loader = self.clone();
+        let icon_name = icon_name.as_ref().to_string();
+
+        match tokio::task::spawn_blocking(move || loader.load_icon(icon_name)).await {
+            Ok(icon) => icon,
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(_) => None,
+        }
+    }
+
+    /// Resolves all icons in `icon_names`, so that their themes are cached for later lookups.
+    /// If retaining preloaded icons is enabled via [`IconLoader::set_retain_preloaded_icons()`],
+    /// the resolved icons are stored and can be retrieved with [`IconLoader::get_preloaded()`].
+    pub fn preload_icons(&self, icon_names: &[impl AsRef<str>]) {
+        for icon_name in icon_names {
+            let icon_name = icon_name.as_ref();
+
+            if let Some(icon) = self.load_icon(icon_name) {
+                if let Some(preloaded_icons) = &self.preloaded_icons {
+                    preloaded_icons.insert(icon_name.to_string(), icon);
+                }
+            }
+        }
     }
-    
+
+    /// Returns the icon with the name `icon_name`, if it was stored by [`IconLoader::preload_icons()`].
+    /// This never touches the filesystem.
+    pub fn get_preloaded(&self, icon_name: &str) -> Option<Icon> {
+        self.preloaded_icons
+            .as_ref()?
+            .get(icon_name)
+            .map(|icon| icon.value().clone())
+    }
+
+    /// Sets whether icons resolved by [`IconLoader::preload_icons()`] are retained in memory.
+    /// Disabling it drops all retained icons. It is disabled by default.
+    pub fn set_retain_preloaded_icons(&mut self, retain: bool) {
+        match (retain, &self.preloaded_icons) {
+            (true, None) => self.preloaded_icons = Some(DashMap::new()),
+            (false, Some(_)) => self.preloaded_icons = None,
+            _ => {}
+        }
+    }
+
+    /// Loads the first icon of `icon_names` that can be found.
+    /// Each name is looked up like in [`IconLoader::load_icon()`].
+    pub fn load_first<I, S>(&self, icon_names: I) -> Option<Icon>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        icon_names
+            .into_iter()
+            .find_map(|icon_name| self.load_icon(icon_name))
+    }
+
+    /// Loads the icon with the name `icon_name` like [`IconLoader::load_icon()`].
+    /// If it cannot be found, the standard placeholder icons `image-missing` and `unknown` are tried instead.
+    pub fn load_icon_or_missing(&self, icon_name: impl AsRef<str>) -> Option<Icon> {
+        self.load_first([icon_name.as_ref(), "image-missing", "unknown"])
+    }
+
+    /// Loads the icon with the name `icon_name` like [`IconLoader::load_icon()`].
+    /// If it cannot be found, the fallback icon set by [`IconLoader::set_fallback_icon()`] is loaded instead.
+    /// Returns `None` if neither icon can be found or no fallback icon is set.
+    pub fn load_icon_or_fallback(&self, icon_name: impl AsRef<str>) -> Option<Icon> {
+        self.load_icon(icon_name).or_else(|| {
+            self.fallback_icon_name
+                .as_ref()
+                .and_then(|fallback_icon_name| self.load_icon(fallback_icon_name))
+        })
+    }
+
+    /// Sets the icon that [`IconLoader::load_icon_or_fallback()`] returns for icons that cannot be found,
+    /// e.g. `image-missing` or `application-x-executable`.
+    pub fn set_fallback_icon(&mut self, icon_name: &str) {
+        self.fallback_icon_name = Some(icon_name.into());
+    }
+
+    /// Loads the symbolic variant `{icon_name}-symbolic` of an icon, falling back to `icon_name`
+    /// if there is none. [`Icon::icon_name()`] of the returned icon tells which variant was found.
+    pub fn load_symbolic_icon(&self, icon_name: &str) -> Option<Icon> {
+        if icon_name.is_empty() {
+            return None;
+        }
+
+        self.load_first([format!("{}-symbolic", icon_name).as_str(), icon_name])
+    }
+
+    /// Loads the icon of a MIME type, given either as MIME type like `text/x-python`
+    /// or as icon name like `text-x-python`. If there is no specific icon, the generic icons
+    /// of the media type are tried like the freedesktop spec describes, e.g. `text-x-generic`
+    /// followed by `text-plain` for text files and `application-x-generic` for applications.
+    pub fn load_mime_icon(&self, mime_or_name: &str) -> Option<Icon> {
+        let icon_name = mime_or_name.replacen('/', "-", 1);
+        let (media_type, _) = icon_name.split_once('-')?;
+
+        let mut icon_names = vec![icon_name.clone(), format!("{}-x-generic", media_type)];
+
+        if media_type == "text" {
+            icon_names.push(String::from("text-plain"));
+        }
+
+        self.load_first(icon_names)
+    }
+
     pub fn themes(&self) -> Arc<ThemeCache> {
         self.theme_cache.clone()
     }
@@ -95,6 +721,129 @@ impl IconLoader {
         &self.theme_name
     }
 
+    /// Uses the theme `theme_name` from now on, if it exists. Otherwise
+    /// [`Error::ThemeNotFound`](crate::Error::ThemeNotFound) is returned and the theme name is kept.
+    /// The theme name provider is replaced by [`ThemeNameProvider::User`] with the new theme name,
+    /// so [`IconLoader::update_theme_name()`] does not revert it.
+    pub fn set_theme_name(&mut self, theme_name: impl Into<String>) -> Result<()> {
+        let theme_name = theme_name.into();
+
+        if !self.theme_exists(&theme_name) {
+            return Err(Error::theme_not_found(theme_name));
+        }
+
+        self.theme_name_provider = ThemeNameProvider::user(theme_name.clone());
+
+        if self.theme_name != theme_name {
+            self.theme_name = theme_name;
+            self.theme_cache.set_current_theme(&self.theme_name);
+            self.clear_preloaded_icons();
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `ThemeNameProvider` that is queried by [`IconLoader::update_theme_name()`],
+    /// e.g. to [invalidate](ThemeNameProvider::invalidate) a cached custom provider.
+    pub fn theme_name_provider(&self) -> &ThemeNameProvider {
+        &self.theme_name_provider
+    }
+
+    /// Sets a new `ThemeNameProvider`. Call [`IconLoader::update_theme_name()`] to apply it.
+    pub fn set_theme_name_provider(&mut self, theme_name_provider: impl Into<ThemeNameProvider>) {
+        self.theme_name_provider = theme_name_provider.into();
+    }
+
+    /// Queries the `ThemeNameProvider` for a new theme name and uses it from now on.
+    /// If no theme with that name exists, the [`ThemeNotFoundPolicy`] decides whether an error
+    /// is returned or the `hicolor` base theme is used instead.
+    /// If a default theme name is set, it is used instead of returning an error or applying the policy.
+    ///
+    /// See also [`IconLoader::set_theme_not_found_policy()`] and [`IconLoader::set_default_theme_name()`].
+    pub fn update_theme_name(&mut self) -> Result<()> {
+        let theme_name = self.provided_theme_name()?;
+
+        if self.theme_name != theme_name {
+            self.theme_name = theme_name;
+            self.theme_cache.set_current_theme(&self.theme_name);
+            self.clear_preloaded_icons();
+        }
+
+        Ok(())
+    }
+
+    /// Clears the theme cache and queries the `ThemeNameProvider` for a new theme name like
+    /// [`IconLoader::update_theme_name()`], e.g. after the user switched themes or installed new icons.
+    /// A [cached custom provider](ThemeNameProvider::cached_custom) is invalidated first,
+    /// so it is queried again as well. The cache is cleared even if an error is returned.
+    pub fn reload(&mut self) -> Result<()> {
+        self.theme_name_provider.invalidate();
+        self.clear_cache();
+
+        self.update_theme_name()
+    }
+
+    /// Queries the `ThemeNameProvider` for the theme name and returns whether it differs from the current
+    /// theme name, without changing it. Use this to check cheaply, e.g. when the application gains focus,
+    /// whether [`IconLoader::update_theme_name()`] has to be called.
+    /// A provided theme that does not exist counts as unchanged if `hicolor` is used in its place.
+    /// Always returns `false` for [`ThemeNameProvider::User`].
+    pub fn theme_name_changed(&self) -> Result<bool> {
+        if let ThemeNameProvider::User(_) = self.theme_name_provider {
+            return Ok(false);
+        }
+
+        if self.default_theme_name.is_some() {
+            return Ok(self.provided_theme_name()? != self.theme_name);
+        }
+
+        let theme_name = self.theme_name_provider.theme_name()?;
+        let replaced_by_base = self.theme_not_found_policy == ThemeNotFoundPolicy::FallbackToBase
+            && self.theme_name == "hicolor"
+            && !self.theme_exists(&theme_name);
+
+        Ok(theme_name != self.theme_name && !replaced_by_base)
+    }
+
+    /// Sets what [`IconLoader::update_theme_name()`] does if the provided theme does not exist.
+    /// The default is [`ThemeNotFoundPolicy::Error`].
+    pub fn set_theme_not_found_policy(&mut self, policy: ThemeNotFoundPolicy) {
+        self.theme_not_found_policy = policy;
+    }
+
+    /// Returns the theme name that is used if the `ThemeNameProvider` fails, if any.
+    ///
+    /// See also [`IconLoader::set_default_theme_name()`].
+    pub fn default_theme_name(&self) -> Option<&str> {
+        self.default_theme_name.as_deref()
+    }
+
+    /// Sets the theme name [`IconLoader::update_theme_name()`] uses if the `ThemeNameProvider` returns
+    /// an error or a theme that does not exist, instead of returning an error or applying the
+    /// [`ThemeNotFoundPolicy`]. This respects the system theme if it can be detected and falls back
+    /// to a theme of the application's choice otherwise.
+    pub fn set_default_theme_name(&mut self, theme_name: impl Into<String>) {
+        self.default_theme_name = Some(theme_name.into());
+    }
+
+    /// Returns the order in which the ancestors of a theme are searched.
+    ///
+    /// See also [`IconLoader::set_traversal_order()`].
+    pub fn traversal_order(&self) -> TraversalOrder {
+        self.traversal_order
+    }
+
+    /// Sets the order in which the ancestors of a theme are searched.
+    /// The default is [`TraversalOrder::DepthFirst`].
+    pub fn set_traversal_order(&mut self, traversal_order: TraversalOrder) {
+        if self.traversal_order == traversal_order {
+            return;
+        }
+
+        self.traversal_order = traversal_order;
+        self.clear_preloaded_icons();
+    }
+
     /// Returns the currently used fallback theme name.
     ///
     /// See also [`IconLoader::set_fallback_theme_name()`].
@@ -103,10 +852,149 @@ impl IconLoader {
     }
 
     /// Returns the paths that are searched for icon themes.
-    pub fn search_paths(&self) -> Cow<[PathBuf]> {
+    pub fn search_paths(&self) -> Cow<'_, [PathBuf]> {
         self.theme_cache.search_paths()
     }
 
+    /// Sets the paths that are searched for icon themes. This clears the theme cache.
+    pub fn set_search_paths(&mut self, search_paths: impl Into<SearchPaths>) {
+        let search_paths = search_paths.into();
+
+        if self.theme_cache.search_paths == search_paths {
+            return;
+        }
+
+        self.theme_cache = Arc::new(ThemeCache::new(
+            search_paths,
+            self.theme_cache.options.clone(),
+        ));
+        self.theme_cache.set_current_theme(&self.theme_name);
+        self.clear_preloaded_icons();
+
+        // Watchers of the previous search paths must not evict from the new cache.
+        #[cfg(feature = "watch")]
+        {
+            self.watched_cache = Default::default();
+        }
+    }
+
+    /// Adds `path` in front of the current search paths, so that its themes take precedence.
+    /// The system search paths are resolved to concrete directories if necessary.
+    /// This clears the theme cache.
+    pub fn prepend_search_path(&mut self, path: impl Into<PathBuf>) {
+        let mut search_paths = vec![path.into()];
+        search_paths.extend(self.search_paths().iter().cloned());
+
+        self.set_search_paths(search_paths);
+    }
+
+    /// Adds `path` after the current search paths, so that it is searched last.
+    /// The system search paths are resolved to concrete directories if necessary.
+    /// This clears the theme cache.
+    pub fn append_search_path(&mut self, path: impl Into<PathBuf>) {
+        let mut search_paths = self.search_paths().into_owned();
+        search_paths.push(path.into());
+
+        self.set_search_paths(search_paths);
+    }
+
+    /// Sets the search paths to the ones given by the environment variables at the time of the call.
+    /// See [`SearchPaths::from_env()`] for details. This clears the theme cache.
+    pub fn set_search_paths_from_env(&mut self) {
+        self.set_search_paths(SearchPaths::from_env());
+    }
+
+    /// Watches the current search paths and evicts cached themes whenever their files change,
+    /// so that later lookups see installed, removed or modified icons.
+    /// Rapid successive changes, like a theme being installed, are combined into a single eviction.
+    /// Watching stops when the returned [`WatchGuard`](crate::WatchGuard) is dropped
+    /// or the search paths are changed. Other setters that clear the theme cache keep it running.
+    /// Icons retained by [`IconLoader::preload_icons()`] are not affected.
+    #[cfg(feature = "watch")]
+    pub fn enable_auto_reload(&self) -> Result<crate::WatchGuard> {
+        crate::watch::watch(&self.theme_cache, &self.watched_cache)
+    }
+
+    /// Sets whether theme directories may match the theme name case-insensitively,
+    /// e.g. a theme named `Breeze` is found in the directory `breeze`.
+    /// The exact name is always preferred. Since this requires scanning the search paths if
+    /// no exact match exists, it is disabled by default. This clears the theme cache.
+    pub fn set_case_insensitive_theme_names(&mut self, case_insensitive: bool) {
+        self.update_theme_options(|options| options.case_insensitive_theme_names = case_insensitive);
+    }
+
+    /// Sets whether icon names are matched case-insensitively, e.g. `Firefox` finds `firefox.png`.
+    /// Exact matches are not preferred; all matching files are returned.
+    /// Since this requires scanning every theme directory instead of checking for a single file,
+    /// it is disabled by default. This clears the theme cache.
+    pub fn set_case_insensitive_icon_names(&mut self, case_insensitive: bool) {
+        self.update_theme_options(|options| options.case_insensitive_icon_names = case_insensitive);
+    }
+
+    /// Limits how many candidate paths a single icon lookup checks on disk, which bounds the latency
+    /// of lookups on slow storage like network filesystems. Once the limit is reached, no further
+    /// directories are searched and `None` is returned if the icon was not found yet.
+    /// With case-insensitive icon names, every scanned directory counts as one path.
+    /// Lookups are unbounded by default.
+    pub fn set_max_probes_per_lookup(&mut self, max_probes: Option<usize>) {
+        self.max_probes_per_lookup = max_probes;
+    }
+
+    /// Sets the directory contexts that are preferred, highest priority first, e.g. `["Status", "Panel"]`.
+    /// Among the files of a loaded icon that fit a size equally well, [`Icon::file_for_size()`] and
+    /// similar methods return the one from the context with the highest priority, regardless of its
+    /// file type.
+    /// There is no context preference by default.
+    pub fn set_context_priority(&mut self, contexts: &[&str]) {
+        self.context_priority = contexts.iter().map(|context| context.to_string()).collect();
+        self.clear_preloaded_icons();
+    }
+
+    /// Restricts the file types that are looked for when discovering icon files, e.g. to only
+    /// load SVG files. Files of other types are never probed, which saves filesystem accesses.
+    /// All file types are allowed by default. This clears the theme cache.
+    pub fn set_allowed_file_types(&mut self, types: &[IconFileType]) {
+        self.update_theme_options(|options| options.allowed_types = Some(types.to_vec()));
+    }
+
+    /// Sets the theme every theme implicitly inherits from after its own parents, which is `hicolor`
+    /// by default as required by the freedesktop icon theme spec. Use `None` for self-contained themes
+    /// that must never fall back to icons of other themes. This clears the theme cache.
+    pub fn set_implicit_fallback_theme(&mut self, theme_name: Option<String>) {
+        self.update_theme_options(|options| options.implicit_fallback_theme = theme_name);
+    }
+
+    /// Sets whether lines of `index.theme` files that cannot be parsed are skipped instead of
+    /// discarding the whole theme, so icons of themes with minor corruption can still be loaded.
+    /// With the `theme_error_log` feature, every skipped line is logged as a warning.
+    /// Parsing is strict by default. This clears the theme cache.
+    pub fn set_lenient_parsing(&mut self, lenient: bool) {
+        self.update_theme_options(|options| options.lenient_parsing = lenient);
+    }
+
+    /// Clears the theme cache, including icon names remembered as missing.
+    /// Call this after icon themes were modified on disk.
+    pub fn clear_cache(&self) {
+        self.theme_cache.cache.clear();
+        self.clear_preloaded_icons();
+    }
+
+    /// Limits the number of themes kept in the theme cache. Once it is exceeded,
+    /// the least recently used theme is removed. Icons that were already loaded stay valid.
+    /// The current theme, the theme that is being loaded and their parents are always kept,
+    /// so the cache may exceed a capacity smaller than their number.
+    /// The cache is unbounded by default. This clears the theme cache.
+    pub fn set_theme_cache_capacity(&mut self, capacity: Option<usize>) {
+        self.update_theme_options(|options| options.capacity = capacity);
+    }
+
+    /// Removes all themes from the theme cache that were not accessed within `max_idle`,
+    /// e.g. to reduce the memory usage of long-running processes that browse many themes once.
+    /// Icons that were already loaded stay valid. Themes are never removed automatically.
+    pub fn trim_cache(&self, max_idle: Duration) {
+        self.theme_cache.trim(max_idle);
+    }
+
     /// Sets a new fallback theme name. If an icon cannot be found in the set theme,
     /// it will be looked for in the fallback theme.
     /// The default fallback theme name is 'hicolor'.
@@ -118,6 +1006,230 @@ impl IconLoader {
         }
 
         self.fallback_theme_name = fallback_theme_name;
+        self.clear_preloaded_icons();
+    }
+
+    /// Sets whether icons that cannot be found in any theme are looked for in the pixmaps directory,
+    /// which contains icon files like `name.png` without any theme structure.
+    /// Such icons have a single [`IconFile`] with a size of 0 and the theme name `pixmaps`.
+    /// It is disabled by default.
+    ///
+    /// See also [`IconLoader::set_pixmaps_path()`].
+    pub fn set_pixmaps_fallback(&mut self, enabled: bool) {
+        if self.pixmaps_fallback == enabled {
+            return;
+        }
+
+        self.pixmaps_fallback = enabled;
+        self.clear_preloaded_icons();
+    }
+
+    /// Sets the pixmaps directory used by [`IconLoader::set_pixmaps_fallback()`].
+    /// The default is `/usr/share/pixmaps`.
+    pub fn set_pixmaps_path(&mut self, pixmaps_path: impl Into<PathBuf>) {
+        let pixmaps_path = pixmaps_path.into();
+
+        if self.pixmaps_path == pixmaps_path {
+            return;
+        }
+
+        self.pixmaps_path = pixmaps_path;
+        self.clear_preloaded_icons();
+    }
+
+    /// Returns the chain of the theme `theme_name`, which is loaded and cached on demand like
+    /// when loading icons, e.g. to inspect its installations, directories and parents.
+    /// Returns `None` if the theme does not exist in any search path.
+    pub fn theme_chain(&self, theme_name: &str) -> Option<Arc<IconThemeChain>> {
+        if theme_name.is_empty() {
+            return None;
+        }
+
+        Some(self.theme_cache.theme(theme_name)).filter(|chain| !chain.is_empty())
+    }
+
+    /// Returns the names of all themes in the exact order [`IconLoader::load_icon_from_theme()`]
+    /// searches them for `theme_name`: the theme itself, its ancestors in the [`TraversalOrder`] of the
+    /// loader, the implicit `hicolor` theme, followed by the fallback theme and its ancestors.
+    /// Each theme is listed once.
+    pub fn resolved_theme_chain(&self, theme_name: &str) -> Vec<String> {
+        if theme_name.is_empty() {
+            return Vec::new();
+        }
+
+        self.search_order(theme_name, self.fallback_theme_name())
+    }
+
+    /// Returns the names of the themes that are searched after the current theme and its ancestors,
+    /// in the order they are searched: the fallback theme followed by its ancestors.
+    /// Themes that were already searched as part of the current theme's ancestry are omitted,
+    /// which always includes the implicit `hicolor` theme.
+    pub fn fallback_order(&self) -> Vec<String> {
+        let primary_order = self.search_order(self.theme_name(), self.theme_name());
+        let mut order = self.search_order(self.theme_name(), self.fallback_theme_name());
+
+        order.split_off(primary_order.len())
+    }
+
+    /// Returns the names of the parent themes of `theme_name` as declared by `Inherits`,
+    /// followed by the implicit `hicolor` theme. The parent themes themselves are not loaded.
+    pub fn theme_parents(&self, theme_name: &str) -> Vec<String> {
+        if theme_name.is_empty() {
+            return Vec::new();
+        }
+
+        self.theme_cache.theme(theme_name).parent_names().to_vec()
+    }
+
+    /// Returns the smallest and largest nominal icon size offered by the directories of the current theme
+    /// and its ancestors, or `None` if they contain no directories. Directories with a size of 0 are ignored.
+    pub fn theme_size_range(&self) -> Option<(u16, u16)> {
+        let sizes = self
+            .search_order(self.theme_name(), self.theme_name())
+            .into_iter()
+            .flat_map(|theme_name| {
+                self.theme_cache
+                    .theme(&theme_name)
+                    .themes()
+                    .iter()
+                    .flat_map(|theme| theme.dirs().iter().map(|dir| dir.size()))
+                    .collect::<Vec<_>>()
+            })
+            .filter(|size| *size != 0);
+
+        sizes.fold(None, |range, size| match range {
+            Some((min, max)) => Some((size.min(min), size.max(max))),
+            None => Some((size, size)),
+        })
+    }
+
+    /// Returns the sorted names of all icons of the theme `theme_name` that live in directories
+    /// with the given context, e.g. `"MimeTypes"`. Parent themes are not considered.
+    pub fn icon_names_in_context(&self, theme_name: &str, context: &str) -> Vec<String> {
+        if theme_name.is_empty() {
+            return Vec::new();
+        }
+
+        let mut names: Vec<String> = self
+            .theme_cache
+            .theme(theme_name)
+            .themes()
+            .iter()
+            .flat_map(|theme| theme.icon_names_in_context(context))
+            .collect();
+
+        names.sort_unstable();
+        names.dedup();
+
+        names
+    }
+
+    /// Returns the names of at most `limit` icons that can be loaded with [`IconLoader::load_icon()`]
+    /// and contain `query`, ignoring case. Names equal to the query come first, followed by names
+    /// starting with it, names containing it at the start of a `-` separated word and all others.
+    /// Names of the same rank are ordered by length, then alphabetically.
+    /// Since this reads every icon directory of the current theme, its parents and the fallback theme,
+    /// it is meant for icon pickers and similar tools rather than frequent calls.
+    pub fn search_icons(&self, query: &str, limit: usize) -> Vec<String> {
+        let query = query.to_lowercase();
+        let mut names = HashSet::new();
+
+        for theme_name in self.search_order(self.theme_name(), &self.fallback_theme_name) {
+            for theme in self.theme_cache.theme(&theme_name).themes() {
+                names.extend(theme.icon_names());
+            }
+        }
+
+        let mut matches: Vec<_> = names
+            .into_iter()
+            .filter_map(|name| {
+                let lowercase_name = name.to_lowercase();
+                let position = lowercase_name.find(&query)?;
+                let rank = if lowercase_name == query {
+                    0
+                } else if position == 0 {
+                    1
+                } else if lowercase_name.split('-').any(|word| word.starts_with(&query)) {
+                    2
+                } else {
+                    3
+                };
+
+                Some((rank, name.len(), name))
+            })
+            .collect();
+
+        matches.sort_unstable();
+
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, _, name)| name)
+            .collect()
+    }
+
+    /// Calls `f` with the icon name and file of every icon file of the theme `theme_name`,
+    /// without collecting them first. Parent themes are not considered. Only files of the allowed
+    /// file types are visited. Once `f` returns `Some`, the walk stops and that value is returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use icon_loader::IconLoader;
+    ///
+    /// let loader = IconLoader::new_hicolor();
+    /// let mut count = 0;
+    /// loader.walk_theme("hicolor", |_icon_name, _icon_file| {
+    ///     count += 1;
+    ///     None::<()>
+    /// });
+    /// ```
+    pub fn walk_theme<T>(
+        &self,
+        theme_name: &str,
+        mut f: impl FnMut(&str, &IconFile) -> Option<T>,
+    ) -> Option<T> {
+        if theme_name.is_empty() {
+            return None;
+        }
+
+        let chain = self.theme_cache.theme(theme_name);
+        let allowed_types = self.theme_cache.options.allowed_types();
+
+        for (icon_name, file) in chain.themes().iter().flat_map(IconTheme::iter_entries) {
+            if allowed_types.contains(&file.icon_type()) {
+                if let Some(value) = f(&icon_name, &file) {
+                    return Some(value);
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Returns the sorted names of all themes installed in the current search paths.
+    /// Themes are listed once, even if they appear in multiple search paths. Directories without
+    /// a readable `index.theme` or without icon directories, like the `default` theme that only
+    /// inherits another theme, are skipped, as are themes marked with `Hidden=true`.
+    /// `hicolor` is only listed if `include_hicolor` is true.
+    pub fn available_themes(&self, include_hicolor: bool) -> Vec<String> {
+        let lenient = self.theme_cache.options.lenient_parsing;
+        let mut theme_names: Vec<String> = self
+            .search_paths()
+            .iter()
+            .flat_map(|search_path| fs::read_dir(search_path).into_iter().flatten().flatten())
+            .filter(|entry| {
+                IconTheme::from_dir(entry.path(), &mut Vec::new(), lenient)
+                    .is_ok_and(|theme| !theme.dirs().is_empty() && !theme.is_hidden())
+            })
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|theme_name| include_hicolor || theme_name != "hicolor")
+            .collect();
+
+        theme_names.sort_unstable();
+        theme_names.dedup();
+
+        theme_names
     }
 
     /// Returns whether a theme with the name `theme_name` exists in the current search paths.
@@ -131,34 +1243,362 @@ impl IconLoader {
         !self.theme_cache.theme(theme_name).is_empty()
     }
 
+    /// Returns whether this loader can find any icons at all, i.e. whether at least one search path
+    /// exists and the current theme or the fallback theme can be found in the search paths.
+    pub fn is_usable(&self) -> bool {
+        self.search_paths().iter().any(|path| path.is_dir())
+            && (self.theme_exists(self.theme_name())
+                || self.theme_exists(self.fallback_theme_name()))
+    }
+
+    /// Captures the current state of the icon themes used by this loader.
+    /// The current theme, the fallback theme and all of their ancestors are loaded beforehand,
+    /// so that the snapshot contains everything [`IconLoader::load_icon()`] would look at.
+    ///
+    /// See also [`IconLoader::load_icon_from_snapshot()`].
+    pub fn snapshot(&self) -> ThemeSnapshot {
+        // Walking the search order loads every theme that could be searched into the cache.
+        self.search_order(self.theme_name(), self.fallback_theme_name());
+
+        ThemeSnapshot::new(
+            self.theme_cache
+                .cache
+                .iter()
+                .map(|entry| entry.value().chain.clone()),
+        )
+    }
+
+    /// Loads the icon with the name `icon_name` from a snapshot taken with [`IconLoader::snapshot()`].
+    /// Themes are searched in the same order as in [`IconLoader::load_icon()`], but the filesystem
+    /// is never touched, so the result does not change when themes are modified on disk.
+    /// The allowed file types and case-insensitive icon names are applied as in
+    /// [`IconLoader::load_icon()`]. The pixmaps fallback and the entry resolver are not used,
+    /// since both look for files at the time of the lookup.
+    /// Found icons are passed to the resolution observer like in [`IconLoader::load_icon()`].
+    pub fn load_icon_from_snapshot(
+        &self,
+        snapshot: &ThemeSnapshot,
+        icon_name: impl AsRef<str>,
+    ) -> Option<Icon> {
+        let icon_name = icon_name.as_ref();
+
+        if self.theme_name.is_empty() || icon_name.is_empty() {
+            return None;
+        }
+
+        let icon = self.search_themes(
+            self.theme_name(),
+            self.fallback_theme_name(),
+            |theme_name| snapshot.parents(theme_name).to_vec(),
+            |theme_name| {
+                snapshot.find_icon(
+                    theme_name,
+                    icon_name,
+                    self.theme_cache.options.allowed_types(),
+                    self.theme_cache.options.case_insensitive_icon_names,
+                )
+            },
+        );
+
+        self.found(icon)
+    }
+
+    /// Sets a function that finds the files of an icon in a theme, instead of looking for
+    /// `<theme dir>/<icon dir>/<icon name>.<ext>` as the freedesktop icon theme spec describes.
+    /// Use this to support icon themes with other layouts, e.g. flat directories with the size in the
+    /// file names, while still choosing files with [`Icon::file_for_size()`] and similar methods.
+    /// The themes still need an `index.theme` file, which may declare no directories if the theme
+    /// inherits from another one. Files of types that are not allowed are dropped.
+    /// This clears the theme cache.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use icon_loader::{IconDir, IconFile, IconFileType, IconLoader};
+    ///
+    /// let mut loader = IconLoader::new("my-assets", "my-assets");
+    /// // Icons are stored as `<theme dir>/<icon name>-<size>.png`.
+    /// loader.set_entry_resolver(Box::new(|theme, icon_name| {
+    ///     [16, 32, 64]
+    ///         .iter()
+    ///         .map(|size| {
+    ///             // The files are located directly in the theme directory.
+    ///             let dir = Arc::new(IconDir::with_size("", *size));
+    ///             let path = theme.content_dir().join(format!("{}-{}.png", icon_name, size));
+    ///             (dir, path)
+    ///         })
+    ///         .filter(|(_, path)| path.is_file())
+    ///         .map(|(dir, path)| IconFile::new(dir, path, IconFileType::PNG))
+    ///         .collect()
+    /// }));
+    /// ```
+    pub fn set_entry_resolver(&mut self, resolver: EntryResolver) {
+        self.update_theme_options(|options| {
+            options.entry_resolver = Some(SharedEntryResolver(Arc::new(resolver)));
+        });
+    }
+
+    /// Sets a callback that is invoked with the icon name and the name of the theme it was found in
+    /// whenever an icon is found. Replaces any previously set callback.
+    pub fn set_resolution_observer(&mut self, observer: ResolutionObserver) {
+        self.resolution_observer = Some(observer);
+    }
+
+    /// Calls [`search_themes()`] with the traversal order and implicit fallback theme of this loader.
+    fn search_themes<T>(
+        &self,
+        theme_name: &str,
+        fallback_theme_name: &str,
+        parents: impl Fn(&str) -> Vec<String>,
+        f: impl FnMut(&str) -> Option<T>,
+    ) -> Option<T> {
+        search_themes(
+            theme_name,
+            fallback_theme_name,
+            self.traversal_order,
+            self.theme_cache.options.implicit_fallback_theme.as_deref(),
+            parents,
+            f,
+        )
+    }
+
+    /// Returns the names of all themes in the order they are searched, starting at `theme_name`.
+    fn search_order(&self, theme_name: &str, fallback_theme_name: &str) -> Vec<String> {
+        let mut order = Vec::new();
+
+        self.search_themes(
+            theme_name,
+            fallback_theme_name,
+            |theme_name| self.theme_cache.theme(theme_name).parents.clone(),
+            |theme_name| {
+                order.push(theme_name.to_string());
+                None::<()>
+            },
+        );
+
+        order
+    }
+
+    /// Queries the `ThemeNameProvider` for a theme name and uses the default theme name if it fails.
+    /// Applies the [`ThemeNotFoundPolicy`] if no theme with that name exists and there is no default.
+    fn provided_theme_name(&self) -> Result<String> {
+        let theme_name = match self.theme_name_provider.theme_name() {
+            Ok(theme_name) if self.theme_exists(&theme_name) => return Ok(theme_name),
+            Ok(theme_name) => theme_name,
+            Err(error) => match &self.default_theme_name {
+                Some(default_theme_name) => {
+                    #[cfg(feature = "theme_error_log")]
+                    log::warn!("{}, using default theme {}", error, default_theme_name);
+
+                    return Ok(default_theme_name.clone());
+                }
+                None => return Err(error.into()),
+            },
+        };
+
+        if let Some(default_theme_name) = &self.default_theme_name {
+            #[cfg(feature = "theme_error_log")]
+            log::warn!(
+                "Theme {} not found, using default theme {}",
+                theme_name,
+                default_theme_name
+            );
+
+            return Ok(default_theme_name.clone());
+        }
+
+        match self.theme_not_found_policy {
+            ThemeNotFoundPolicy::Error => Err(Error::theme_not_found(theme_name)),
+            ThemeNotFoundPolicy::FallbackToBase => {
+                #[cfg(feature = "theme_error_log")]
+                log::warn!("Theme {} not found, falling back to hicolor", theme_name);
+
+                Ok(String::from("hicolor"))
+            }
+        }
+    }
+
+    /// Applies `f` to the theme options and replaces the theme cache with an empty one using them.
+    fn update_theme_options(&mut self, f: impl FnOnce(&mut ThemeOptions)) {
+        let mut options = self.theme_cache.options.clone();
+        f(&mut options);
+
+        self.theme_cache = Arc::new(ThemeCache::new(
+            self.theme_cache.search_paths.clone(),
+            options,
+        ));
+        self.theme_cache.set_current_theme(&self.theme_name);
+        self.clear_preloaded_icons();
+
+        #[cfg(feature = "watch")]
+        {
+            *self.watched_cache.write().unwrap() = Arc::downgrade(&self.theme_cache);
+        }
+    }
+
+    fn clear_preloaded_icons(&self) {
+        if let Some(preloaded_icons) = &self.preloaded_icons {
+            preloaded_icons.clear();
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip(self), fields(found_in, candidates))
+    )]
     fn find_icon(&self, theme_name: &str, icon_name: &str) -> Option<Icon> {
         if theme_name.is_empty() || icon_name.is_empty() {
             return None;
         }
 
-        let mut searched_themes = vec![];
+        let mut probes = self.max_probes_per_lookup;
+        let icon = self.search_themes(
+            theme_name,
+            &self.fallback_theme_name,
+            |theme_name| self.theme_cache.theme(theme_name).parents.clone(),
+            |theme_name| {
+                self.theme_cache
+                    .theme(theme_name)
+                    .find_icon(icon_name, &mut probes)
+            },
+        )
+        .or_else(|| self.find_pixmap(icon_name));
+
+        #[cfg(feature = "tracing")]
+        if let Some(icon) = &icon {
+            let span = tracing::Span::current();
+            span.record("found_in", icon.theme_name());
+            span.record("candidates", icon.files().len());
+        }
+
+        self.found(icon)
+    }
+
+    /// Looks for `icon_name` in the pixmaps directory, if the pixmaps fallback is enabled.
+    fn find_pixmap(&self, icon_name: &str) -> Option<Icon> {
+        if !self.pixmaps_fallback {
+            return None;
+        }
 
-        let themes = self.theme_cache.theme(theme_name);
-        let fallback_themes = self.theme_cache.theme(&self.fallback_theme_name);
-        let mut themes = VecDeque::from([themes, fallback_themes]);
+        let allowed_types = self.theme_cache.options.allowed_types();
+        let (path, icon_type) = allowed_types.iter().find_map(|icon_type| {
+            let path = self.pixmaps_path.join(icon_name).with_extension(icon_type.as_ref());
+
+            path.is_file().then_some((path, *icon_type))
+        })?;
+
+        flat_icon(icon_name, PIXMAPS_THEME_NAME, path, icon_type)
+    }
+
+    /// Applies the context priority to a found icon and passes it to the resolution observer.
+    fn found(&self, mut icon: Option<Icon>) -> Option<Icon> {
+        if let Some(icon) = &mut icon {
+            icon.set_context_priority(&self.context_priority);
+
+            if let Some(observer) = &self.resolution_observer {
+                observer(icon.icon_name(), icon.theme_name());
+            }
+        }
+
+        icon
+    }
+}
+
+impl std::fmt::Debug for IconLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IconLoader")
+            .field("theme_name", &self.theme_name)
+            .field("fallback_theme_name", &self.fallback_theme_name)
+            .field("default_theme_name", &self.default_theme_name)
+            .field("theme_name_provider", &self.theme_name_provider)
+            .field("theme_not_found_policy", &self.theme_not_found_policy)
+            .field("traversal_order", &self.traversal_order)
+            .field("pixmaps_fallback", &self.pixmaps_fallback)
+            .field("pixmaps_path", &self.pixmaps_path)
+            .field("max_probes_per_lookup", &self.max_probes_per_lookup)
+            .field("context_priority", &self.context_priority)
+            .field("fallback_icon_name", &self.fallback_icon_name)
+            .field("theme_cache", &self.theme_cache)
+            .field("preloaded_icons", &self.preloaded_icons)
+            .field("resolution_observer", &self.resolution_observer.is_some())
+            .finish()
+    }
+}
+
+/// Creates an icon consisting of the single file at `path` without theme structure.
+fn flat_icon(
+    icon_name: &str,
+    theme_name: &str,
+    path: PathBuf,
+    icon_type: IconFileType,
+) -> Option<Icon> {
+    let dir_info = Arc::new(IconDir::flat(path.parent()?.to_path_buf()));
+
+    Icon::new(
+        icon_name.into(),
+        theme_name.into(),
+        vec![IconFile::new(dir_info, path, icon_type)],
+    )
+}
+
+/// Visits the theme `theme_name` and its ancestors, then the fallback theme and its ancestors,
+/// in the given order and returns the first value `f` returns for any of them.
+/// The `implicit_parent` theme is deferred until all other ancestors of a theme were visited.
+pub(crate) fn search_themes<T>(
+    theme_name: &str,
+    fallback_theme_name: &str,
+    order: TraversalOrder,
+    implicit_parent: Option<&str>,
+    parents: impl Fn(&str) -> Vec<String>,
+    mut f: impl FnMut(&str) -> Option<T>,
+) -> Option<T> {
+    let mut searched_themes = vec![];
+
+    for root in [theme_name, fallback_theme_name] {
+        let mut themes = VecDeque::from([root.to_string()]);
+        let mut implicit_parent_pending = false;
+
+        loop {
+            let theme_name = match themes.pop_front() {
+                Some(theme_name) => theme_name,
+                None => match implicit_parent {
+                    Some(implicit_parent) if implicit_parent_pending => {
+                        implicit_parent_pending = false;
+                        implicit_parent.to_string()
+                    }
+                    _ => break,
+                },
+            };
 
-        while let Some(theme) = themes.pop_front() {
-            let theme_name = theme.name().to_string();
             if searched_themes.contains(&theme_name) {
                 continue;
             }
 
-            theme.parents().for_each(|theme| themes.push_front(theme));
+            let mut theme_parents = parents(&theme_name);
+            theme_parents.retain(|parent| {
+                let is_implicit = Some(parent.as_str()) == implicit_parent;
+                implicit_parent_pending |= is_implicit;
+                !is_implicit
+            });
+
+            match order {
+                TraversalOrder::DepthFirst => theme_parents
+                    .into_iter()
+                    .rev()
+                    .for_each(|parent| themes.push_front(parent)),
+                TraversalOrder::BreadthFirst => themes.extend(theme_parents),
+            }
 
-            if let Some(icon) = theme.find_icon(icon_name) {
-                return Some(icon);
+            if let Some(value) = f(&theme_name) {
+                return Some(value);
             }
 
             searched_themes.push(theme_name);
         }
-
-        None
     }
+
+    None
 }
 
 impl Default for IconLoader {
@@ -0,0 +1,104 @@
+use crate::{
+    error::Result, icon::IconFileType, search_paths::SearchPaths,
+    theme_name_provider::ThemeNameProvider, IconLoader, ThemeNotFoundPolicy,
+};
+
+/// Builder that configures an [`IconLoader`] and queries its theme name once when it is built.
+///
+/// # Example
+///
+/// ```no_run
+/// use icon_loader::{IconFileType, IconLoaderBuilder, ThemeNotFoundPolicy};
+///
+/// let loader = IconLoaderBuilder::new()
+///     .theme_name_provider("my-theme")
+///     .theme_not_found_policy(ThemeNotFoundPolicy::FallbackToBase)
+///     .search_paths(["/opt/my-app/icons"])
+///     .allowed_file_types(&[IconFileType::SVG])
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct IconLoaderBuilder {
+    theme_name_provider: Option<ThemeNameProvider>,
+    fallback_theme_name: Option<String>,
+    default_theme_name: Option<String>,
+    theme_not_found_policy: ThemeNotFoundPolicy,
+    search_paths: Option<SearchPaths>,
+    allowed_file_types: Option<Vec<IconFileType>>,
+}
+
+impl IconLoaderBuilder {
+    /// Creates a builder for a loader that uses the `hicolor` theme and the system search paths.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the provider of the theme name. The default is the `hicolor` theme.
+    pub fn theme_name_provider(mut self, theme_name_provider: impl Into<ThemeNameProvider>) -> Self {
+        self.theme_name_provider = Some(theme_name_provider.into());
+        self
+    }
+
+    /// Sets the theme that is searched if an icon cannot be found in the current theme.
+    /// The default is `hicolor`.
+    pub fn fallback_theme_name(mut self, fallback_theme_name: impl Into<String>) -> Self {
+        self.fallback_theme_name = Some(fallback_theme_name.into());
+        self
+    }
+
+    /// Sets the theme that is used if the provider fails or the provided theme does not exist.
+    /// See [`IconLoader::set_default_theme_name()`].
+    pub fn default_theme_name(mut self, default_theme_name: impl Into<String>) -> Self {
+        self.default_theme_name = Some(default_theme_name.into());
+        self
+    }
+
+    /// Sets what happens if the provided theme does not exist when the loader is built.
+    /// The default is [`ThemeNotFoundPolicy::Error`].
+    pub fn theme_not_found_policy(mut self, policy: ThemeNotFoundPolicy) -> Self {
+        self.theme_not_found_policy = policy;
+        self
+    }
+
+    /// Sets the paths that are searched for icon themes. The default is [`SearchPaths::System`].
+    pub fn search_paths(mut self, search_paths: impl Into<SearchPaths>) -> Self {
+        self.search_paths = Some(search_paths.into());
+        self
+    }
+
+    /// Restricts the file types that are looked for. All file types are allowed by default.
+    /// See [`IconLoader::set_allowed_file_types()`].
+    pub fn allowed_file_types(mut self, types: &[IconFileType]) -> Self {
+        self.allowed_file_types = Some(types.to_vec());
+        self
+    }
+
+    /// Creates the loader and queries the theme name from the theme name provider.
+    /// Returns an error if the provider fails, see [`IconLoader::update_theme_name()`].
+    pub fn build(self) -> Result<IconLoader> {
+        let fallback_theme_name = self.fallback_theme_name.as_deref().unwrap_or("hicolor");
+        let mut loader = IconLoader::new("hicolor", fallback_theme_name);
+        loader.set_theme_not_found_policy(self.theme_not_found_policy);
+
+        if let Some(default_theme_name) = self.default_theme_name {
+            loader.set_default_theme_name(default_theme_name);
+        }
+
+        if let Some(search_paths) = self.search_paths {
+            loader.set_search_paths(search_paths);
+        }
+
+        if let Some(types) = &self.allowed_file_types {
+            loader.set_allowed_file_types(types);
+        }
+
+        if let Some(theme_name_provider) = self.theme_name_provider {
+            loader.set_theme_name_provider(theme_name_provider);
+        }
+
+        loader.update_theme_name()?;
+
+        Ok(loader)
+    }
+}
@@ -1,14 +1,24 @@
-use std::{borrow::Cow, path::PathBuf};
+use std::{borrow::Cow, env, ffi::OsString, path::PathBuf};
 
-use xdg::BaseDirectories;
+/// Looks up the value of the environment variable with the given name.
+pub(crate) type EnvLookup<'a> = &'a dyn Fn(&str) -> Option<OsString>;
 
 /// Enum that provides a list of directories to [`IconLoader`](crate::IconLoader) to search for icons in.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub enum SearchPaths {
-    /// Uses the `xdg` crate for system icon paths.
+    /// Uses the platform's default icon paths.
+    ///
+    /// On unix systems the freedesktop icon paths are searched, see [`SearchPaths::from_env()`].
+    /// On macOS `~/Library/Application Support/icons` and `/opt/homebrew/share/icons` are searched as well.
+    /// On Windows `%APPDATA%\icons` and the `icons` directory next to the executable are searched.
     #[default]
     System,
 
+    /// Uses the platform's default data paths like [`SearchPaths::System`], but with the given
+    /// subdirectory instead of `icons`, e.g. `$XDG_DATA_DIRS/<prefix>`.
+    /// `$HOME/.icons` is only searched if the prefix is `icons`.
+    SystemWithPrefix(String),
+
     /// A custom set of paths.
     Custom(Vec<PathBuf>),
 }
@@ -23,17 +33,116 @@ impl SearchPaths {
         SearchPaths::Custom(iter.into_iter().map(P::into).collect())
     }
 
-    pub(crate) fn paths(&self) -> Cow<[PathBuf]> {
+    /// Creates a `SearchPaths` that uses the given subdirectory of the platform's data paths
+    /// instead of `icons`, see [`SearchPaths::SystemWithPrefix`].
+    pub fn system_with_prefix(prefix: impl Into<String>) -> Self {
+        SearchPaths::SystemWithPrefix(prefix.into())
+    }
+
+    /// Creates a custom `SearchPaths` containing the directory `subdir` next to the running executable,
+    /// e.g. for portable applications that ship their icon theme.
+    /// If the path of the executable cannot be determined, no paths are searched.
+    pub fn relative_to_exe(subdir: &str) -> Self {
+        SearchPaths::custom(
+            env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(|dir| dir.join(subdir))),
+        )
+    }
+
+    /// Creates a custom `SearchPaths` from the environment variables at the time of the call,
+    /// in the order mandated by the freedesktop icon theme spec: `$HOME/.icons` first,
+    /// then `$XDG_DATA_HOME/icons`, followed by the `icons` directory of every entry of `$XDG_DATA_DIRS`.
+    /// Unset or empty variables fall back to the spec defaults `$HOME/.local/share` and
+    /// `/usr/local/share:/usr/share`. Relative paths are ignored, as required by the XDG base directory spec.
+    pub fn from_env() -> Self {
+        Self::from_env_with(&|key| env::var_os(key))
+    }
+
+    /// Like [`SearchPaths::from_env()`], but looks up environment variables with `var`.
+    pub(crate) fn from_env_with(var: EnvLookup<'_>) -> Self {
+        SearchPaths::Custom(xdg_paths("icons", var))
+    }
+
+    pub(crate) fn paths(&self) -> Cow<'_, [PathBuf]> {
+        self.paths_with_env(&|key| env::var_os(key))
+    }
+
+    /// Like [`SearchPaths::paths()`], but looks up environment variables with `var`.
+    pub(crate) fn paths_with_env(&self, var: EnvLookup<'_>) -> Cow<'_, [PathBuf]> {
         match self {
-            SearchPaths::System => Cow::Owned(BaseDirectories::with_prefix("icons").map_or_else(
-                |_| vec![PathBuf::from("/usr/share/icons")],
-                |bd| bd.get_data_dirs(),
-            )),
+            SearchPaths::System => Cow::Owned(system_paths("icons", var)),
+            SearchPaths::SystemWithPrefix(prefix) => Cow::Owned(system_paths(prefix, var)),
             SearchPaths::Custom(dirs) => Cow::Borrowed(dirs),
         }
     }
 }
 
+/// Returns `$HOME/.icons` if `prefix` is `icons`, then `$XDG_DATA_HOME/<prefix>`,
+/// followed by `$XDG_DATA_DIRS/<prefix>`, looking up environment variables with `var`.
+/// Unset or empty variables and relative paths are handled as described in [`SearchPaths::from_env()`].
+fn xdg_paths(prefix: &str, var: EnvLookup<'_>) -> Vec<PathBuf> {
+    let var = |key: &str| var(key).filter(|value| !value.is_empty());
+    let home = var("HOME").map(PathBuf::from);
+
+    let mut paths = Vec::new();
+
+    if let Some(home) = home.as_ref().filter(|_| prefix == "icons") {
+        paths.push(home.join(".icons"));
+    }
+
+    match var("XDG_DATA_HOME").map(PathBuf::from).filter(|dir| dir.is_absolute()) {
+        Some(data_home) => paths.push(data_home.join(prefix)),
+        None => paths.extend(home.map(|home| home.join(".local/share").join(prefix))),
+    }
+
+    let data_dirs = var("XDG_DATA_DIRS").unwrap_or_else(|| "/usr/local/share:/usr/share".into());
+    paths.extend(
+        env::split_paths(&data_dirs)
+            .filter(|dir| dir.is_absolute())
+            .map(|dir| dir.join(prefix)),
+    );
+
+    paths
+}
+
+/// Returns the system icon paths in the order mandated by the freedesktop icon theme spec:
+/// `$HOME/.icons` first, then `$XDG_DATA_HOME/<prefix>`, followed by `$XDG_DATA_DIRS/<prefix>`.
+/// Platform specific locations are appended afterwards. Environment variables are looked up with `var`.
+#[cfg_attr(not(any(unix, windows)), allow(unused_variables))]
+fn system_paths(prefix: &str, var: EnvLookup<'_>) -> Vec<PathBuf> {
+    #[cfg_attr(not(any(unix, windows)), allow(unused_mut))]
+    let mut paths = Vec::new();
+
+    #[cfg(unix)]
+    paths.extend(xdg_paths(prefix, var));
+
+    #[cfg(target_os = "macos")]
+    {
+        if let Some(home) = var("HOME") {
+            paths.push(PathBuf::from(home).join("Library/Application Support").join(prefix));
+        }
+
+        paths.push(PathBuf::from("/opt/homebrew/share").join(prefix));
+    }
+
+    #[cfg(windows)]
+    {
+        if let Some(app_data) = var("APPDATA") {
+            paths.push(PathBuf::from(app_data).join(prefix));
+        }
+
+        if let Some(exe_dir) = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join(prefix)))
+        {
+            paths.push(exe_dir);
+        }
+    }
+
+    paths
+}
+
 impl<I, P> From<I> for SearchPaths
 where
     I: IntoIterator<Item = P>,
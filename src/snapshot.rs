@@ -0,0 +1,112 @@
+use crate::icon::{icon_theme::merge_entries, Icon, IconFile, IconFileType, IconThemeChain};
+
+use std::{collections::HashMap, sync::Arc};
+
+/// A fixed snapshot of the icon themes known to an [`IconLoader`](crate::IconLoader).
+///
+/// Icons resolved from a snapshot with [`IconLoader::load_icon_from_snapshot()`](crate::IconLoader::load_icon_from_snapshot)
+/// never touch the filesystem, so two lookups of the same name always yield identical paths.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThemeSnapshot {
+    themes: HashMap<String, SnapshotTheme>,
+}
+
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct SnapshotTheme {
+    parents: Vec<String>,
+    icons: HashMap<String, Vec<IconFile>>,
+}
+
+impl ThemeSnapshot {
+    pub(crate) fn new(chains: impl IntoIterator<Item = Arc<IconThemeChain>>) -> Self {
+        let mut themes = HashMap::new();
+
+        for chain in chains {
+            let mut icons: HashMap<String, Vec<IconFile>> = HashMap::new();
+
+            for theme in chain.themes() {
+                let mut entries: Vec<(String, IconFile)> = theme.iter_entries().collect();
+
+                // Keep the same file order as a lookup on disk would produce.
+                for dir_entries in entries.chunk_by_mut(|(_, a), (_, b)| {
+                    std::ptr::eq(a.dir_info(), b.dir_info())
+                }) {
+                    dir_entries.sort_by_key(|(_, file)| {
+                        IconFileType::types()
+                            .iter()
+                            .position(|icon_type| *icon_type == file.icon_type())
+                    });
+                }
+
+                let mut theme_icons: HashMap<String, Vec<IconFile>> = HashMap::new();
+
+                for (name, file) in entries {
+                    theme_icons.entry(name).or_default().push(file);
+                }
+
+                for (name, files) in theme_icons {
+                    merge_entries(icons.entry(name).or_default(), files);
+                }
+            }
+
+            let theme = SnapshotTheme {
+                parents: chain.parents.clone(),
+                icons,
+            };
+
+            themes.insert(chain.name().to_string(), theme);
+        }
+
+        Self { themes }
+    }
+
+    /// Returns the names of all themes contained in this snapshot.
+    pub fn theme_names(&self) -> impl Iterator<Item = &str> {
+        self.themes.keys().map(String::as_str)
+    }
+
+    /// Returns whether the theme with the name `theme_name` is contained in this snapshot.
+    pub fn contains_theme(&self, theme_name: &str) -> bool {
+        self.themes.contains_key(theme_name)
+    }
+
+    pub(crate) fn parents(&self, theme_name: &str) -> &[String] {
+        self.themes
+            .get(theme_name)
+            .map_or(&[], |theme| theme.parents.as_slice())
+    }
+
+    /// Finds the files of `icon_name` in the theme `theme_name` that have one of the `icon_types`.
+    /// With `case_insensitive`, the files of all icon names matching `icon_name` are returned.
+    pub(crate) fn find_icon(
+        &self,
+        theme_name: &str,
+        icon_name: &str,
+        icon_types: &[IconFileType],
+        case_insensitive: bool,
+    ) -> Option<Icon> {
+        let icons = &self.themes.get(theme_name)?.icons;
+
+        let mut files: Vec<IconFile> = if case_insensitive {
+            let lowercase_name = icon_name.to_lowercase();
+            let mut names: Vec<&String> = icons
+                .keys()
+                .filter(|name| name.to_lowercase() == lowercase_name)
+                .collect();
+            names.sort();
+
+            names
+                .into_iter()
+                .flat_map(|name| icons[name].iter().cloned())
+                .collect()
+        } else {
+            icons.get(icon_name)?.clone()
+        };
+
+        files.retain(|file| icon_types.contains(&file.icon_type()));
+
+        Icon::new(icon_name.into(), theme_name.into(), files)
+    }
+}
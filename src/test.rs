@@ -1,6 +1,67 @@
 #[cfg(test)]
 mod test {
-    use crate::{IconFileType, IconLoader};
+    use crate::{
+        Error, Icon, IconDir, IconFile, IconFileType, IconLoader, IconLoaderBuilder, IconSizeType,
+        ProviderError, SearchPaths, ThemeNameProvider, ThemeNotFoundPolicy, TraversalOrder,
+    };
+
+    use std::{
+        env, fs,
+        path::Path,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    /// Creates the theme `theme_name` in `root` with the given `index.theme` contents
+    /// and empty icon files at the given paths relative to the theme directory.
+    fn create_theme(root: &Path, theme_name: &str, index: &str, files: &[&str]) {
+        let theme_dir = root.join(theme_name);
+        fs::create_dir_all(&theme_dir).unwrap();
+        fs::write(theme_dir.join("index.theme"), index).unwrap();
+
+        for file in files {
+            let path = theme_dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, []).unwrap();
+        }
+    }
+
+    /// Builds an icon with one file per directory section of the given `index.theme` contents.
+    /// Scalable directories contain an SVG file, all others a PNG file.
+    fn icon_from_index(index: &str) -> Icon {
+        let ini = ini::Ini::load_from_str(index).unwrap();
+
+        let files = ini
+            .iter()
+            .filter_map(|(section, properties)| {
+                let dir_info = Arc::new(IconDir::new(section?.into(), properties));
+                let icon_type = match dir_info.size_type() {
+                    IconSizeType::Scalable => IconFileType::SVG,
+                    _ => IconFileType::PNG,
+                };
+                let path = dir_info.path().join("icon").with_extension(icon_type.as_ref());
+
+                Some(IconFile::new(dir_info, path, icon_type))
+            })
+            .collect();
+
+        Icon::new("icon".into(), "test".into(), files).unwrap()
+    }
+
+    /// Returns an environment variable lookup that only knows the given variables.
+    #[cfg(any(unix, all(feature = "kde", feature = "gtk")))]
+    fn fake_env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<std::ffi::OsString> {
+        let vars: Vec<(String, String)> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        move |key| {
+            vars.iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.into())
+        }
+    }
 
     #[test]
     fn test_find_firefox_icon() {
@@ -13,4 +74,2609 @@ mod test {
         assert_eq!(icon.dir_info().scale(), 1);
         assert_eq!(icon.icon_type(), IconFileType::PNG);
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_system_search_path_order() {
+        let env = fake_env(&[("HOME", "/home/user")]);
+        let paths = SearchPaths::System.paths_with_env(&env).into_owned();
+
+        assert_eq!(paths[0], Path::new("/home/user/.icons"));
+        assert_eq!(paths[1], Path::new("/home/user/.local/share/icons"));
+        assert!(paths.len() > 2);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_system_search_paths_with_prefix() {
+        let env = fake_env(&[("HOME", "/home/user")]);
+        let paths = SearchPaths::system_with_prefix("vendor/icons")
+            .paths_with_env(&env)
+            .into_owned();
+        let icon_paths = SearchPaths::system_with_prefix("icons")
+            .paths_with_env(&env)
+            .into_owned();
+        let system_paths = SearchPaths::System.paths_with_env(&env).into_owned();
+
+        assert_eq!(paths[0], Path::new("/home/user/.local/share/vendor/icons"));
+        assert!(paths.iter().all(|path| path.ends_with("vendor/icons")));
+        assert_eq!(icon_paths, system_paths);
+    }
+
+    #[test]
+    fn test_search_paths_relative_to_exe() {
+        let exe_dir = env::current_exe().unwrap().parent().unwrap().to_path_buf();
+
+        assert_eq!(
+            SearchPaths::relative_to_exe("share/icons"),
+            SearchPaths::custom([exe_dir.join("share/icons")])
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_search_paths_from_env() {
+        let custom = SearchPaths::from_env_with(&fake_env(&[
+            ("HOME", "/home/user"),
+            ("XDG_DATA_HOME", "/data/home"),
+            ("XDG_DATA_DIRS", "/data/a:relative:/data/b"),
+        ]));
+        let defaults =
+            SearchPaths::from_env_with(&fake_env(&[("HOME", "/home/user"), ("XDG_DATA_DIRS", "")]));
+
+        assert_eq!(
+            custom,
+            SearchPaths::custom([
+                "/home/user/.icons",
+                "/data/home/icons",
+                "/data/a/icons",
+                "/data/b/icons",
+            ])
+        );
+        assert_eq!(
+            defaults,
+            SearchPaths::custom([
+                "/home/user/.icons",
+                "/home/user/.local/share/icons",
+                "/usr/local/share/icons",
+                "/usr/share/icons",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_load_icon_from_snapshot() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/app.png", "16x16/apps/app.svg", "16x16/apps/Other.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        let snapshot = loader.snapshot();
+
+        assert!(snapshot.contains_theme("test"));
+        assert_eq!(
+            loader.load_icon_from_snapshot(&snapshot, "app"),
+            loader.load_icon("app")
+        );
+
+        loader.set_allowed_file_types(&[IconFileType::SVG]);
+        loader.set_case_insensitive_icon_names(true);
+        let icon = loader.load_icon_from_snapshot(&snapshot, "app").unwrap();
+        assert_eq!(icon.files().len(), 1);
+        assert_eq!(icon.files()[0].icon_type(), IconFileType::SVG);
+        assert!(loader.load_icon_from_snapshot(&snapshot, "other").is_none());
+
+        loader.set_allowed_file_types(&[IconFileType::PNG]);
+        assert_eq!(
+            loader.load_icon_from_snapshot(&snapshot, "other"),
+            loader.load_icon("other")
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_snapshot_serde_round_trip() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\nInherits=parent\n\n[16x16/apps]\nSize=16\n\n\
+             [scalable/apps]\nSize=16\nMaxSize=256\nType=Scalable\nContext=Applications\n",
+            &["16x16/apps/app.png", "scalable/apps/app.svg"],
+        );
+        create_theme(
+            root.path(),
+            "parent",
+            "[Icon Theme]\nName=Parent\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/other.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        let snapshot = loader.snapshot();
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: crate::ThemeSnapshot = serde_json::from_str(&json).unwrap();
+
+        assert!(restored.contains_theme("parent"));
+        for icon_name in ["app", "other"] {
+            let icon = loader.load_icon_from_snapshot(&restored, icon_name);
+            assert!(icon.is_some());
+            assert_eq!(icon, loader.load_icon_from_snapshot(&snapshot, icon_name));
+        }
+    }
+
+    #[test]
+    fn test_scalable_size_range() {
+        let icon = icon_from_index(
+            "[16x16/apps]\nSize=16\nType=Fixed\n\n\
+             [scalable/apps]\nSize=16\nMinSize=8\nMaxSize=256\nType=Scalable\n\n\
+             [512x512/apps]\nSize=512\nType=Fixed\n",
+        );
+
+        assert_eq!(icon.file_for_size(16).dir_info().path().to_str(), Some("16x16/apps"));
+        assert_eq!(icon.file_for_size(48).dir_info().path().to_str(), Some("scalable/apps"));
+        assert_eq!(icon.file_for_size(600).dir_info().path().to_str(), Some("512x512/apps"));
+        assert_eq!(icon.try_file_for_size(48), Some(icon.file_for_size(48)));
+        assert_eq!(icon.try_file_for_size_scaled(48, 2), Some(icon.file_for_size_scaled(48, 2)));
+    }
+
+    #[test]
+    fn test_size_distance_selection() {
+        let icon = icon_from_index(
+            "[22x22/apps]\nSize=22\nType=Fixed\n\n\
+             [48x48/apps]\nSize=48\nType=Fixed\n\n\
+             [24x24@2/apps]\nSize=24\nScale=2\nType=Fixed\n\n\
+             [64x64/apps]\nSize=64\nThreshold=2\nType=Threshold\n\n\
+             [scalable/apps]\nSize=128\nMinSize=96\nMaxSize=256\nType=Scalable\n\n\
+             [256x256/apps]\nSize=256\nType=Fixed\n",
+        );
+
+        let path_for = |size, scale| {
+            icon.file_for_size_scaled(size, scale)
+                .dir_info()
+                .path()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(path_for(32, 1), "22x22/apps");
+        assert_eq!(path_for(40, 1), "48x48/apps");
+        assert_eq!(path_for(63, 1), "64x64/apps");
+        assert_eq!(path_for(128, 1), "scalable/apps");
+        assert_eq!(path_for(24, 2), "24x24@2/apps");
+        assert_eq!(path_for(24, 1), "22x22/apps");
+        assert_eq!(path_for(48, 1), "48x48/apps");
+    }
+
+    #[test]
+    fn test_whitespace_in_index_keys() {
+        // The ini parser trims keys and values, which keeps hand-edited themes working.
+        let ini = ini::Ini::load_from_str("[32x32/apps]\n\tSize = 32\n  Scale\t=2\n\tType=Fixed \n").unwrap();
+        let dir_info = IconDir::new("32x32/apps".into(), ini.section(Some("32x32/apps")).unwrap());
+
+        assert_eq!(dir_info.size(), 32);
+        assert_eq!(dir_info.scale(), 2);
+        assert_eq!(dir_info.size_type(), IconSizeType::Fixed);
+    }
+
+    #[test]
+    fn test_unknown_size_type() {
+        let ini = ini::Ini::load_from_str("[a]\nSize=32\nType=Scaleable\n\n[b]\nSize=32\nType=Threshold\n").unwrap();
+        let unknown = IconDir::new("a".into(), ini.section(Some("a")).unwrap());
+        let known = IconDir::new("b".into(), ini.section(Some("b")).unwrap());
+
+        assert_eq!(unknown.size_type(), IconSizeType::Threshold);
+        assert_eq!(unknown.unknown_size_type(), Some("Scaleable"));
+        assert_eq!(known.unknown_size_type(), None);
+    }
+
+    #[test]
+    fn test_preload_icons() {
+        let mut loader = IconLoader::new_hicolor();
+        loader.preload_icons(&["firefox"]);
+        assert_eq!(loader.get_preloaded("firefox"), None);
+
+        loader.set_retain_preloaded_icons(true);
+        loader.preload_icons(&["firefox", "does-not-exist"]);
+
+        assert_eq!(loader.get_preloaded("firefox"), loader.load_icon("firefox"));
+        assert_eq!(loader.get_preloaded("does-not-exist"), None);
+    }
+
+    #[test]
+    fn test_file_within_tolerance() {
+        let icon = icon_from_index("[24x24/apps]\nSize=24\nType=Fixed\n\n[48x48/apps]\nSize=48\nType=Fixed\n");
+
+        assert_eq!(icon.file_within_tolerance(32, 25).map(IconFile::size), Some(24));
+        assert_eq!(icon.file_within_tolerance(36, 10), None);
+        assert_eq!(icon.file_within_tolerance(48, 0).map(IconFile::size), Some(48));
+
+        // Thresholds and size ranges do not widen the tolerance.
+        let icon = icon_from_index(
+            "[48x48/apps]\nSize=48\nType=Threshold\nThreshold=2\n\n\
+             [scalable/apps]\nSize=64\nType=Scalable\nMinSize=16\nMaxSize=256\n",
+        );
+        assert_eq!(icon.file_within_tolerance(46, 0), None);
+        assert_eq!(icon.file_within_tolerance(46, 5).map(IconFile::size), Some(48));
+        assert_eq!(icon.file_within_tolerance(128, 50).map(IconFile::size), Some(64));
+        assert_eq!(icon.file_within_tolerance(128, 40), None);
+    }
+
+    #[test]
+    fn test_missing_icons_are_cached() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/foo.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        assert!(loader.load_icon("foo").is_some());
+        assert!(loader.load_icon("bar").is_none());
+
+        fs::write(root.path().join("test/16x16/apps/bar.png"), []).unwrap();
+        assert!(loader.load_icon("bar").is_none());
+
+        loader.clear_cache();
+        assert!(loader.load_icon("bar").is_some());
+    }
+
+    #[test]
+    fn test_case_insensitive_theme_names() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "breeze",
+            "[Icon Theme]\nName=Breeze\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/foo.png"],
+        );
+
+        let mut loader = IconLoader::new("Breeze", "Breeze");
+        loader.set_search_paths([root.path()]);
+        assert!(loader.load_icon("foo").is_none());
+
+        loader.set_case_insensitive_theme_names(true);
+        assert!(loader.load_icon("foo").is_some());
+    }
+
+    #[test]
+    fn test_resolved_theme_chain() {
+        let root = tempfile::tempdir().unwrap();
+        let dirs = "[16x16/apps]\nSize=16\n";
+        create_theme(
+            root.path(),
+            "child",
+            &format!("[Icon Theme]\nInherits=parent\n\n{}", dirs),
+            &[],
+        );
+        create_theme(
+            root.path(),
+            "parent",
+            &format!("[Icon Theme]\nInherits=grandparent\n\n{}", dirs),
+            &[],
+        );
+        create_theme(root.path(), "grandparent", &format!("[Icon Theme]\n\n{}", dirs), &[]);
+        create_theme(root.path(), "fallback", &format!("[Icon Theme]\n\n{}", dirs), &[]);
+
+        let mut loader = IconLoader::new("child", "fallback");
+        loader.set_search_paths([root.path()]);
+
+        // The implicit hicolor theme is searched after all declared ancestors.
+        assert_eq!(
+            loader.resolved_theme_chain("child"),
+            ["child", "parent", "grandparent", "hicolor", "fallback"]
+        );
+    }
+
+    #[test]
+    fn test_fallback_order() {
+        let root = tempfile::tempdir().unwrap();
+        let dirs = "[16x16/apps]\nSize=16\n";
+        let inherits = |parents: &str| format!("[Icon Theme]\nInherits={}\n\n{}", parents, dirs);
+        create_theme(root.path(), "child", &inherits("shared"), &[]);
+        create_theme(root.path(), "shared", dirs, &[]);
+        create_theme(root.path(), "fallback", &inherits("fallback-parent,shared"), &[]);
+        create_theme(root.path(), "fallback-parent", dirs, &[]);
+
+        let mut loader = IconLoader::new("child", "fallback");
+        loader.set_search_paths([root.path()]);
+
+        // `shared` and `hicolor` were already searched as ancestors of `child`.
+        assert_eq!(loader.fallback_order(), ["fallback", "fallback-parent"]);
+    }
+
+    #[test]
+    fn test_traversal_order() {
+        let root = tempfile::tempdir().unwrap();
+        let dirs = "[16x16/apps]\nSize=16\n";
+        let inherits = |parents: &str| format!("[Icon Theme]\nInherits={}\n\n{}", parents, dirs);
+        create_theme(root.path(), "child", &inherits("first,second"), &[]);
+        create_theme(root.path(), "first", &inherits("base"), &[]);
+        create_theme(root.path(), "second", &inherits("hicolor"), &["16x16/apps/app.png"]);
+        create_theme(root.path(), "base", dirs, &["16x16/apps/app.png"]);
+        create_theme(root.path(), "hicolor", dirs, &["16x16/apps/app.png"]);
+
+        let mut loader = IconLoader::new("child", "child");
+        loader.set_search_paths([root.path()]);
+
+        assert_eq!(loader.traversal_order(), TraversalOrder::DepthFirst);
+        assert_eq!(
+            loader.resolved_theme_chain("child"),
+            ["child", "first", "base", "second", "hicolor"]
+        );
+        assert_eq!(loader.load_icon("app").unwrap().theme_name(), "base");
+
+        loader.set_traversal_order(TraversalOrder::BreadthFirst);
+        assert_eq!(
+            loader.resolved_theme_chain("child"),
+            ["child", "first", "second", "base", "hicolor"]
+        );
+        assert_eq!(loader.load_icon("app").unwrap().theme_name(), "second");
+    }
+
+    /// The id, name and recorded fields of a span.
+    #[cfg(feature = "tracing")]
+    type RecordedSpan = (tracing::span::Id, &'static str, Vec<(String, String)>);
+
+    /// A tracing layer that records the fields of all spans.
+    #[cfg(feature = "tracing")]
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<Mutex<Vec<RecordedSpan>>>);
+
+    #[cfg(feature = "tracing")]
+    struct FieldRecorder<'a>(&'a mut Vec<(String, String)>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for FieldRecorder<'_> {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0.push((field.name().to_string(), value.to_string()));
+        }
+
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push((field.name().to_string(), format!("{:?}", value)));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpanRecorder {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = Vec::new();
+            attrs.record(&mut FieldRecorder(&mut fields));
+            self.0
+                .lock()
+                .unwrap()
+                .push((id.clone(), attrs.metadata().name(), fields));
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut spans = self.0.lock().unwrap();
+
+            // Span ids may be reused once a span is closed, so the latest span with the id is meant.
+            let span = spans.iter_mut().rev().find(|(span_id, _, _)| span_id == id);
+
+            if let Some((_, _, fields)) = span {
+                values.record(&mut FieldRecorder(fields));
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    fn test_tracing_spans() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n\n\
+             [scalable/apps]\nSize=16\nType=Scalable\n",
+            &["16x16/apps/app.png", "scalable/apps/app.svg"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        let recorder = SpanRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        let icon = tracing::subscriber::with_default(subscriber, || loader.load_icon("app"));
+        assert!(icon.is_some());
+
+        let spans = recorder.0.lock().unwrap();
+        let fields = |name: &str| {
+            spans
+                .iter()
+                .find(|(_, span_name, _)| *span_name == name)
+                .map(|(_, _, fields)| fields.clone())
+                .unwrap()
+        };
+        let field = |fields: &[(String, String)], name: &str| {
+            fields
+                .iter()
+                .find(|(field_name, _)| field_name == name)
+                .map(|(_, value)| value.clone())
+        };
+
+        let theme_fields = fields("find");
+        assert_eq!(field(&theme_fields, "theme_name").as_deref(), Some("test"));
+        assert_eq!(field(&theme_fields, "search_paths").as_deref(), Some("1"));
+
+        let lookup_fields = fields("find_icon");
+        assert_eq!(field(&lookup_fields, "theme_name").as_deref(), Some("test"));
+        assert_eq!(field(&lookup_fields, "found_in").as_deref(), Some("test"));
+        assert_eq!(field(&lookup_fields, "candidates").as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn test_resolution_observer() {
+        let root = tempfile::tempdir().unwrap();
+        let dirs = "[16x16/apps]\nSize=16\n";
+        create_theme(
+            root.path(),
+            "child",
+            &format!("[Icon Theme]\nInherits=parent\n\n{}", dirs),
+            &["16x16/apps/own.png"],
+        );
+        create_theme(root.path(), "parent", dirs, &["16x16/apps/inherited.png"]);
+
+        let resolved = Arc::new(Mutex::new(Vec::new()));
+        let observed = resolved.clone();
+        let mut loader = IconLoader::new("child", "child");
+        loader.set_search_paths([root.path()]);
+        loader.set_resolution_observer(Box::new(move |icon_name, theme_name| {
+            observed
+                .lock()
+                .unwrap()
+                .push((icon_name.to_string(), theme_name.to_string()));
+        }));
+
+        assert!(loader.load_icon("inherited").is_some());
+        assert!(loader.load_icon("own").is_some());
+        assert!(loader.load_icon("missing").is_none());
+
+        let snapshot = loader.snapshot();
+        assert!(loader.load_icon_from_snapshot(&snapshot, "inherited").is_some());
+        assert!(loader.load_icon_from_snapshot(&snapshot, "missing").is_none());
+
+        assert_eq!(
+            *resolved.lock().unwrap(),
+            [
+                ("inherited".to_string(), "parent".to_string()),
+                ("own".to_string(), "child".to_string()),
+                ("inherited".to_string(), "parent".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "async")]
+    async fn test_load_icon_async() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/app.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        let loader = Arc::new(loader);
+
+        let icon = loader.load_icon_async("app").await.unwrap();
+        assert_eq!(icon.theme_name(), "test");
+        assert_eq!(Some(icon), loader.load_icon("app"));
+        assert!(loader.load_icon_async("missing").await.is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "watch")]
+    fn test_auto_reload() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n",
+            &[],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        let _guard = loader.enable_auto_reload().unwrap();
+        // Replaces the theme cache, the watcher has to follow it.
+        loader.set_lenient_parsing(true);
+
+        assert!(loader.load_icon("foo").is_none());
+        let path = root.path().join("test/16x16/apps/foo.png");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, []).unwrap();
+
+        // Feed the event to the eviction loop directly instead of waiting for the file system.
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let kind = notify::EventKind::Create(notify::event::CreateKind::File);
+        sender.send(Ok(notify::Event::new(kind).add_path(path))).unwrap();
+        drop(sender);
+        crate::watch::evict_changed_themes(
+            &loader.watched_cache,
+            &[root.path().to_path_buf()],
+            &receiver,
+        );
+
+        assert!(loader.load_icon("foo").is_some());
+    }
+
+    #[test]
+    fn test_file_for_size_within() {
+        let icon = icon_from_index(
+            "[16x16/apps]\nSize=16\nType=Fixed\n\n[32x32/apps]\nSize=32\nType=Fixed\n\n\
+             [48x48/apps]\nSize=48\nType=Fixed\n\n[256x256/apps]\nSize=256\nType=Fixed\n",
+        );
+        let size_within =
+            |size, allowed: &[u16]| icon.file_for_size_within(size, allowed).map(IconFile::size);
+
+        assert_eq!(size_within(32, &[16, 32, 48]), Some(32));
+        assert_eq!(size_within(40, &[16, 48]), Some(48));
+        // Requested sizes outside the allowed sizes get the closest bound.
+        assert_eq!(size_within(256, &[16, 32]), Some(32));
+        assert_eq!(size_within(1, &[32, 48]), Some(32));
+
+        assert_eq!(size_within(32, &[]), None);
+        assert_eq!(size_within(32, &[24, 64]), None);
+    }
+
+    #[test]
+    fn test_large_scaled_sizes_do_not_overflow() {
+        let icon = icon_from_index("[16x16/apps]\nSize=16\nType=Fixed\n\n[512x512/apps]\nSize=512\nType=Fixed\n");
+
+        // 32768 * 2 exceeds u16::MAX and would wrap around to 0 in 16 bit arithmetic.
+        assert_eq!(icon.file_for_size_scaled(32768, 2).size(), 512);
+        assert_eq!(icon.file_for_size_scaled(u16::MAX, u16::MAX).size(), 512);
+        assert_eq!(icon.file_within_tolerance(u16::MAX, 50), None);
+    }
+
+    #[test]
+    fn test_load_symbolic_icon() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/actions]\nSize=16\n",
+            &["16x16/actions/edit-copy.png", "16x16/actions/edit-copy-symbolic.svg", "16x16/actions/edit-cut.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        let copy = loader.load_symbolic_icon("edit-copy").unwrap();
+        assert_eq!(copy.icon_name(), "edit-copy-symbolic");
+
+        let cut = loader.load_symbolic_icon("edit-cut").unwrap();
+        assert_eq!(cut.icon_name(), "edit-cut");
+    }
+
+    #[test]
+    fn test_theme_not_found_policy() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "hicolor",
+            "[Icon Theme]\nName=Hicolor\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/app.png"],
+        );
+
+        let mut loader = IconLoader::new("x", "x");
+        loader.set_search_paths([root.path()]);
+        loader.set_theme_name_provider("uninstalled");
+
+        match loader.update_theme_name() {
+            Err(Error::ThemeNotFound { theme_name }) => assert_eq!(theme_name, "uninstalled"),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert_eq!(loader.theme_name(), "x");
+
+        loader.set_theme_not_found_policy(ThemeNotFoundPolicy::FallbackToBase);
+        loader.update_theme_name().unwrap();
+        assert_eq!(loader.theme_name(), "hicolor");
+    }
+
+    #[test]
+    fn test_default_theme_name() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "system", index, &[]);
+        create_theme(root.path(), "default", index, &[]);
+
+        let mut loader = IconLoader::new("x", "x");
+        loader.set_search_paths([root.path()]);
+        loader.set_theme_not_found_policy(ThemeNotFoundPolicy::FallbackToBase);
+        loader.set_default_theme_name("default");
+        assert_eq!(loader.default_theme_name(), Some("default"));
+
+        loader.set_theme_name_provider("system");
+        loader.update_theme_name().unwrap();
+        assert_eq!(loader.theme_name(), "system");
+
+        loader.set_theme_name_provider("uninstalled");
+        loader.update_theme_name().unwrap();
+        assert_eq!(loader.theme_name(), "default");
+
+        loader.set_theme_name("system").unwrap();
+        loader.set_theme_name_provider(ThemeNameProvider::custom(|| {
+            Err::<String, _>(std::io::Error::other("no desktop"))
+        }));
+        loader.update_theme_name().unwrap();
+        assert_eq!(loader.theme_name(), "default");
+    }
+
+    #[test]
+    fn test_load_icon_from_parents() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "child",
+            "[Icon Theme]\nName=Child\nInherits=parent\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/overridden.png", "16x16/apps/own.png"],
+        );
+        create_theme(
+            root.path(),
+            "parent",
+            "[Icon Theme]\nName=Parent\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/overridden.png", "16x16/apps/inherited.png"],
+        );
+
+        let mut loader = IconLoader::new("x", "x");
+        loader.set_search_paths([root.path()]);
+
+        let overridden = loader.load_icon_from_parents("child", "overridden").unwrap();
+        assert_eq!(overridden.theme_name(), "parent");
+        assert!(loader.load_icon_from_parents("child", "inherited").is_some());
+        assert!(loader.load_icon_from_parents("child", "own").is_none());
+        assert!(loader.load_icon_from_parents("parent", "inherited").is_none());
+    }
+
+    #[cfg(feature = "svg")]
+    #[test]
+    fn test_render_symbolic() {
+        let root = tempfile::tempdir().unwrap();
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16"><rect width="16" height="16" fill="#BEBEBE"/></svg>"##;
+        fs::write(root.path().join("edit-copy-symbolic.svg"), svg).unwrap();
+        fs::write(root.path().join("edit-copy.svg"), svg).unwrap();
+
+        let dir = Arc::new(IconDir::new("".into(), &Default::default()));
+        let symbolic = IconFile::new(
+            dir.clone(),
+            root.path().join("edit-copy-symbolic.svg"),
+            IconFileType::SVG,
+        );
+        let regular = IconFile::new(dir, root.path().join("edit-copy.svg"), IconFileType::SVG);
+
+        let image = symbolic.render_symbolic(32, [255, 0, 0, 255]).unwrap();
+        assert_eq!(image.dimensions(), (32, 32));
+        assert_eq!(image.get_pixel(16, 16).0, [255, 0, 0, 255]);
+
+        assert!(matches!(
+            regular.render_symbolic(32, [255, 0, 0, 255]),
+            Err(Error::NotSymbolic { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pixmaps_fallback() {
+        let root = tempfile::tempdir().unwrap();
+        let pixmaps = root.path().join("pixmaps");
+        fs::create_dir_all(&pixmaps).unwrap();
+        fs::write(pixmaps.join("legacy-app.xpm"), "").unwrap();
+
+        let mut loader = IconLoader::new("x", "x");
+        loader.set_search_paths([root.path().join("icons")]);
+        loader.set_pixmaps_path(&pixmaps);
+        assert!(loader.load_icon("legacy-app").is_none());
+
+        loader.set_pixmaps_fallback(true);
+        let icon = loader.load_icon("legacy-app").unwrap();
+        assert_eq!(icon.theme_name(), "pixmaps");
+        assert_eq!(icon.files().len(), 1);
+        assert_eq!(icon.file_for_size(16).path(), Some(&*pixmaps.join("legacy-app.xpm")));
+        assert_eq!(icon.file_for_size(16).size(), 0);
+        assert!(loader.load_icon("other-app").is_none());
+    }
+
+    #[test]
+    fn test_file_for_size_clamped() {
+        let icon = icon_from_index(
+            "[512x512/apps]\nSize=512\nType=Fixed\n\n[256x256/apps]\nSize=256\nType=Fixed\n\n[64x64/apps]\nSize=64\nType=Fixed\n",
+        );
+
+        assert_eq!(icon.file_for_size(500).size(), 512);
+        assert_eq!(icon.file_for_size_clamped(500, 256).size(), 256);
+        assert_eq!(icon.file_for_size_clamped(512, 32).size(), 512);
+    }
+
+    #[test]
+    fn test_reduced_to_size() {
+        let icon = icon_from_index(
+            "[16x16/apps]\nSize=16\nType=Fixed\n\n[32x32/apps]\nSize=32\nType=Fixed\n",
+        );
+        let file = icon.file_for_size(32).clone();
+        let reduced = icon.clone().reduced_to_size(32);
+
+        assert_eq!(reduced.files(), [file]);
+        assert_eq!(reduced.icon_name(), icon.icon_name());
+        assert_eq!(reduced.theme_name(), icon.theme_name());
+    }
+
+    #[test]
+    fn test_case_insensitive_icon_names() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/firefox.png", "16x16/apps/Firefox.svg"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        assert_eq!(loader.load_icon("FIREFOX"), None);
+
+        loader.set_case_insensitive_icon_names(true);
+        let icon = loader.load_icon("FIREFOX").unwrap();
+        let types: Vec<_> = icon.files().iter().map(IconFile::icon_type).collect();
+        assert_eq!(types, [IconFileType::PNG, IconFileType::SVG]);
+    }
+
+    #[test]
+    fn test_load_icon_with_origin() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "primary", index, &["16x16/apps/own.png"]);
+        create_theme(root.path(), "fallback", index, &["16x16/apps/other.png"]);
+
+        let mut loader = IconLoader::new("primary", "fallback");
+        loader.set_search_paths([root.path()]);
+
+        let (icon, from_fallback) = loader.load_icon_with_origin("own").unwrap();
+        assert_eq!(icon.theme_name(), "primary");
+        assert!(!from_fallback);
+
+        let (icon, from_fallback) = loader.load_icon_with_origin("other").unwrap();
+        assert_eq!(icon.theme_name(), "fallback");
+        assert!(from_fallback);
+    }
+
+    #[test]
+    #[cfg(all(feature = "kde", feature = "gtk"))]
+    fn test_xdg_current_desktop_provider() {
+        let provider = |desktops: &str| {
+            let env = fake_env(&[("XDG_CURRENT_DESKTOP", desktops)]);
+            ThemeNameProvider::for_current_desktop(&env).map(|provider| format!("{:?}", provider))
+        };
+        let gnome = if cfg!(feature = "gsettings") { "GSettings" } else { "GTK" };
+        let xfce = if cfg!(feature = "xfce") { "XFCE" } else { "GTK" };
+
+        assert_eq!(provider("KDE").as_deref(), Some("ThemeNameProvider::KDE"));
+        assert_eq!(provider("GNOME"), Some(format!("ThemeNameProvider::{}", gnome)));
+        assert_eq!(provider("ubuntu:GNOME"), Some(format!("ThemeNameProvider::{}", gnome)));
+        assert_eq!(provider("XFCE"), Some(format!("ThemeNameProvider::{}", xfce)));
+        assert_eq!(provider("UnknownDesktop"), None);
+        assert_eq!(provider(""), None);
+    }
+
+    #[test]
+    fn test_max_probes_per_lookup() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n\n[32x32/apps]\nSize=32\n",
+            &["16x16/apps/app.png", "32x32/apps/app.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        // Checking a single directory takes one probe per file type.
+        loader.set_max_probes_per_lookup(Some(3));
+        assert_eq!(loader.load_icon("app").unwrap().files().len(), 1);
+
+        loader.set_max_probes_per_lookup(None);
+        assert_eq!(loader.load_icon("app").unwrap().files().len(), 2);
+    }
+
+    #[test]
+    fn test_theme_parents() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "child",
+            "[Icon Theme]\nName=Child\nInherits=parent,other\n\n[16x16/apps]\nSize=16\n",
+            &[],
+        );
+
+        let mut loader = IconLoader::new("x", "x");
+        loader.set_search_paths([root.path()]);
+
+        assert_eq!(loader.theme_parents("child"), ["parent", "other", "hicolor"]);
+    }
+
+    #[test]
+    fn test_available_themes() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n";
+        create_theme(first.path(), "breeze", index, &[]);
+        create_theme(first.path(), "hicolor", index, &[]);
+        create_theme(first.path(), "default", "[Icon Theme]\nInherits=breeze\n", &[]);
+        create_theme(second.path(), "breeze", index, &[]);
+        create_theme(second.path(), "adwaita", index, &[]);
+        fs::create_dir(second.path().join("not-a-theme")).unwrap();
+
+        let mut loader = IconLoader::new("x", "x");
+        loader.set_search_paths([first.path(), second.path()]);
+
+        assert_eq!(loader.available_themes(false), ["adwaita", "breeze"]);
+        assert_eq!(loader.available_themes(true), ["adwaita", "breeze", "hicolor"]);
+    }
+
+    #[test]
+    fn test_earlier_search_paths_take_precedence() {
+        let user = tempfile::tempdir().unwrap();
+        let system = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n\n[32x32/apps]\nSize=32\n";
+        create_theme(user.path(), "test", index, &["16x16/apps/app.png"]);
+        create_theme(
+            system.path(),
+            "test",
+            index,
+            &["16x16/apps/app.png", "32x32/apps/app.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([user.path(), system.path()]);
+
+        let icon = loader.load_icon("app").unwrap();
+        let paths: Vec<_> = icon.files().iter().map(|file| file.path().unwrap()).collect();
+        assert_eq!(
+            paths,
+            [
+                user.path().join("test/16x16/apps/app.png"),
+                system.path().join("test/32x32/apps/app.png"),
+            ]
+        );
+
+        let snapshot = loader.snapshot();
+        assert_eq!(loader.load_icon_from_snapshot(&snapshot, "app"), Some(icon));
+    }
+
+    #[test]
+    fn test_is_usable() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "fallback",
+            "[Icon Theme]\nName=Fallback\n\n[16x16/apps]\nSize=16\n",
+            &[],
+        );
+
+        let mut loader = IconLoader::new("missing", "fallback");
+        loader.set_search_paths([root.path().join("does-not-exist")]);
+        assert!(!loader.is_usable());
+
+        loader.set_search_paths([root.path()]);
+        assert!(loader.is_usable());
+
+        loader.set_fallback_theme_name("missing");
+        assert!(!loader.is_usable());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_natural_size() {
+        let root = tempfile::tempdir().unwrap();
+        image::RgbaImage::new(3, 5)
+            .save(root.path().join("raster.png"))
+            .unwrap();
+        fs::write(
+            root.path().join("sized.svg"),
+            r#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" width="24px" height='16' viewBox="0 0 48 32"/>"#,
+        )
+        .unwrap();
+        fs::write(
+            root.path().join("view-box.svg"),
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0,0 22 22"></svg>"#,
+        )
+        .unwrap();
+
+        let dir_info = icon_from_index("[16x16/apps]\nSize=16\n").files()[0].dir_info().clone();
+        let natural_size = |name: &str, icon_type| {
+            IconFile::new(Arc::new(dir_info.clone()), root.path().join(name), icon_type).natural_size()
+        };
+
+        assert_eq!(natural_size("raster.png", IconFileType::PNG), Some((3, 5)));
+        assert_eq!(natural_size("sized.svg", IconFileType::SVG), Some((24, 16)));
+        assert_eq!(natural_size("view-box.svg", IconFileType::SVG), Some((22, 22)));
+        assert_eq!(natural_size("missing.png", IconFileType::PNG), None);
+    }
+
+    #[test]
+    fn test_best_file() {
+        let icon = icon_from_index(
+            "[16x16/apps]\nSize=16\nType=Fixed\nContext=Apps\n\n\
+             [scalable/apps]\nSize=16\nType=Scalable\nMinSize=8\nMaxSize=512\nContext=Apps\n\n\
+             [32x32@2/actions]\nSize=32\nScale=2\nType=Fixed\nContext=Actions\n",
+        );
+
+        let path = |file: Option<&IconFile>| file.map(|file| file.dir_info().path().to_owned());
+
+        assert_eq!(path(icon.best_file().size(16).resolve()), Some("16x16/apps".into()));
+        assert_eq!(
+            path(icon.best_file().size(16).prefer_type(IconFileType::SVG).resolve()),
+            Some("scalable/apps".into())
+        );
+        assert_eq!(
+            path(icon.best_file().size(32).scale(2).resolve()),
+            Some("32x32@2/actions".into())
+        );
+        assert_eq!(
+            path(icon.best_file().size(32).scale(2).context("Apps").resolve()),
+            Some("scalable/apps".into())
+        );
+        assert_eq!(icon.best_file().context("MimeTypes").resolve(), None);
+    }
+
+    #[cfg(all(feature = "kde", feature = "gtk"))]
+    #[test]
+    fn test_providers_with_config_dir() {
+        let config_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(config_dir.path().join("gtk-3.0")).unwrap();
+        fs::write(config_dir.path().join("kdeglobals"), "[Icons]\nTheme=breeze\n").unwrap();
+        fs::write(
+            config_dir.path().join("gtk-3.0/settings.ini"),
+            "[Settings]\ngtk-icon-theme-name=Adwaita\n",
+        )
+        .unwrap();
+
+        let kde = IconLoader::new_from_provider(ThemeNameProvider::kde_with_config_dir(config_dir.path()));
+        let gtk = IconLoader::new_from_provider(ThemeNameProvider::gtk_with_config_dir(config_dir.path()));
+        assert_eq!(kde.unwrap().theme_name(), "breeze");
+        assert_eq!(gtk.unwrap().theme_name(), "Adwaita");
+
+        let missing = config_dir.path().join("missing");
+        assert!(matches!(
+            IconLoader::new_from_provider(ThemeNameProvider::kde_with_config_dir(missing)),
+            Err(Error::ThemeNameProvider {
+                source: ProviderError::ConfigNotFound
+            })
+        ));
+    }
+
+    #[test]
+    fn test_trim_cache() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "old", index, &["16x16/apps/app.png"]);
+        create_theme(root.path(), "recent", index, &[]);
+
+        let mut loader = IconLoader::new("recent", "recent");
+        loader.set_search_paths([root.path()]);
+
+        let icon = loader.load_icon_from_theme("old", "app").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        loader.load_icon("app");
+
+        loader.trim_cache(Duration::from_millis(25));
+        let snapshot = loader.snapshot();
+        assert!(!snapshot.contains_theme("old"));
+        assert!(snapshot.contains_theme("recent"));
+        assert_eq!(loader.load_icon_from_theme("old", "app"), Some(icon));
+    }
+
+    #[test]
+    fn test_replaced_theme_cache_is_dropped() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\nInherits=parent\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/app.png"],
+        );
+        let index = "[Icon Theme]\nName=Parent\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "parent", index, &[]);
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        assert!(loader.load_icon("missing").is_none());
+
+        // Cached themes must not keep the cache they are stored in alive.
+        let old_cache = Arc::downgrade(&loader.themes());
+        loader.set_lenient_parsing(true);
+        assert!(old_cache.upgrade().is_none());
+
+        assert!(loader.load_icon("missing").is_none());
+        let old_cache = Arc::downgrade(&loader.themes());
+        loader.set_search_paths([root.path().join("other")]);
+        assert!(old_cache.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_theme_cache_capacity() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n";
+        for theme_name in ["first", "second", "third"] {
+            create_theme(root.path(), theme_name, index, &["16x16/apps/app.png"]);
+        }
+
+        let mut loader = IconLoader::new("x", "x");
+        loader.set_search_paths([root.path()]);
+        loader.set_theme_cache_capacity(Some(2));
+
+        let icon = loader.themes().theme("first").find_icon("app", &mut None);
+        loader.themes().theme("second");
+        loader.themes().theme("first");
+        loader.themes().theme("third");
+
+        let cached = |theme_name: &str| loader.themes().cache.contains_key(theme_name);
+        assert!(cached("first"));
+        assert!(!cached("second"));
+        assert!(cached("third"));
+        assert!(icon.is_some());
+    }
+
+    #[test]
+    fn test_theme_cache_capacity_keeps_current_chain() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "child",
+            "[Icon Theme]\nName=Child\nInherits=parent\n\n[16x16/apps]\nSize=16\n",
+            &[],
+        );
+        let index = "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "parent", index, &["16x16/apps/app.png"]);
+        create_theme(root.path(), "hicolor", index, &[]);
+        create_theme(root.path(), "other", index, &[]);
+        create_theme(root.path(), "another", index, &[]);
+
+        let mut loader = IconLoader::new("child", "child");
+        loader.set_search_paths([root.path()]);
+        loader.set_theme_cache_capacity(Some(1));
+
+        assert!(loader.load_icon("missing").is_none());
+        loader.themes().theme("other");
+        loader.themes().theme("another");
+        let chain = loader.themes().theme("child");
+        assert!(loader.load_icon("app").is_some());
+
+        let cached = |theme_name: &str| loader.themes().cache.contains_key(theme_name);
+        assert!(cached("child"));
+        assert!(cached("parent"));
+        assert!(cached("hicolor"));
+        assert!(!cached("other"));
+
+        // Lookups reuse the cached chains instead of loading them again.
+        assert!(Arc::ptr_eq(&chain, &loader.themes().theme("child")));
+    }
+
+    #[test]
+    fn test_icon_dir_size_matching() {
+        let ini = ini::Ini::load_from_str(
+            "[fixed]\nSize=32\nType=Fixed\n\n\
+             [scalable]\nSize=32\nType=Scalable\nMinSize=16\nMaxSize=64\n\n\
+             [threshold]\nSize=32\nThreshold=4\n\n\
+             [scaled]\nSize=32\nScale=2\nType=Fixed\n",
+        )
+        .unwrap();
+        let dir = |name: &str| IconDir::new(name.into(), ini.section(Some(name)).unwrap());
+
+        assert!(dir("fixed").matches_size(32, 1));
+        assert!(!dir("fixed").matches_size(31, 1));
+        assert_eq!(dir("fixed").size_distance(24, 1), 8);
+
+        assert!(dir("scalable").matches_size(64, 1));
+        assert_eq!(dir("scalable").size_distance(48, 1), 0);
+        assert_eq!(dir("scalable").size_distance(8, 1), 8);
+
+        assert!(dir("threshold").matches_size(36, 1));
+        assert!(!dir("threshold").matches_size(37, 1));
+        assert_eq!(dir("threshold").size_distance(40, 1), 4);
+
+        assert!(dir("scaled").matches_size(32, 2));
+        assert!(!dir("scaled").matches_size(32, 1));
+        assert_eq!(dir("scaled").size_distance(32, 1), 32);
+    }
+
+    #[test]
+    fn test_theme_size_range() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "child",
+            "[Icon Theme]\nName=Child\nInherits=parent\n\n[22x22/apps]\nSize=22\n",
+            &[],
+        );
+        create_theme(
+            root.path(),
+            "parent",
+            "[Icon Theme]\nName=Parent\n\n[16x16/apps]\nSize=16\n\n[256x256/apps]\nSize=256\n",
+            &[],
+        );
+
+        let mut loader = IconLoader::new("child", "child");
+        loader.set_search_paths([root.path()]);
+        assert_eq!(loader.theme_size_range(), Some((16, 256)));
+
+        let mut loader = IconLoader::new("missing", "missing");
+        loader.set_search_paths([root.path()]);
+        assert_eq!(loader.theme_size_range(), None);
+    }
+
+    #[test]
+    fn test_try_load_icon_errors() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n",
+            &[],
+        );
+        create_theme(root.path(), "broken", "[Icon Theme]\nName=Broken\n", &[]);
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        assert!(matches!(
+            loader.try_load_icon("app"),
+            Err(Error::IconNotFound { icon_name }) if icon_name == "app"
+        ));
+
+        let mut loader = IconLoader::new("broken", "test");
+        loader.set_search_paths([root.path()]);
+        assert!(matches!(
+            loader.try_load_icon("app"),
+            Err(Error::ThemeEmpty { theme_name }) if theme_name == "broken"
+        ));
+    }
+
+    #[test]
+    fn test_add_virtual_theme() {
+        let root = tempfile::tempdir().unwrap();
+        let content_dir = root.path().join("generated");
+        fs::create_dir_all(content_dir.join("16x16/apps")).unwrap();
+        fs::write(content_dir.join("16x16/apps/app.png"), "").unwrap();
+
+        let mut loader = IconLoader::new("virtual", "virtual");
+        loader.set_search_paths([root.path()]);
+        loader
+            .add_virtual_theme(
+                "virtual",
+                "[Icon Theme]\nName=Virtual\nInherits=parent\n\n[16x16/apps]\nSize=16\n",
+                content_dir.clone(),
+            )
+            .unwrap();
+
+        let icon = loader.load_icon("app").unwrap();
+        assert_eq!(icon.theme_name(), "virtual");
+        assert_eq!(icon.files()[0].path(), Some(&*content_dir.join("16x16/apps/app.png")));
+        assert_eq!(loader.theme_parents("virtual"), ["parent", "hicolor"]);
+
+        assert!(matches!(
+            loader.add_virtual_theme("empty", "[Icon Theme]\nName=Empty\n", content_dir),
+            Err(Error::InvalidTheme { theme_name, .. }) if theme_name == "empty"
+        ));
+    }
+
+    #[test]
+    fn test_icons_in_context() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\nContext=Applications\n\n\
+             [32x32/mimetypes]\nSize=32\nContext=MimeTypes\n",
+            &["16x16/apps/app.png", "16x16/apps/shared.png", "32x32/mimetypes/shared.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        assert_eq!(loader.icon_names_in_context("test", "Applications"), ["app", "shared"]);
+        assert_eq!(loader.icon_names_in_context("test", "MimeTypes"), ["shared"]);
+        assert!(loader.icon_names_in_context("test", "Status").is_empty());
+
+        let icon = loader.load_icon("shared").unwrap();
+        let file = icon.file_for_size_in_context(16, "MimeTypes").unwrap();
+        assert_eq!(file.size(), 32);
+        let file = icon.file_for_size_in_context(32, "Applications").unwrap();
+        assert_eq!(file.size(), 16);
+        assert!(icon.file_for_size_in_context(16, "Status").is_none());
+    }
+
+    #[test]
+    fn test_context_priority() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n\
+             [16x16/apps]\nSize=16\nContext=Applications\n\n\
+             [16x16/status]\nSize=16\nContext=Status\n\n\
+             [32x32/panel]\nSize=32\nContext=Panel\n",
+            &["16x16/apps/app.png", "16x16/status/app.png", "32x32/panel/app.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        assert_eq!(loader.load_icon("app").unwrap().file_for_size(16).context(), Some("Applications"));
+
+        let unprioritized = loader.load_icon("app").unwrap();
+        loader.set_context_priority(&["Panel", "Status"]);
+        let icon = loader.load_icon("app").unwrap();
+        assert_eq!(icon.file_for_size(16).context(), Some("Status"));
+        assert_eq!(icon.file_for_size(32).context(), Some("Panel"));
+
+        // The priority only affects file selection, not equality.
+        assert_eq!(icon, unprioritized);
+    }
+
+    #[test]
+    fn test_context_priority_over_file_type() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n\
+             [16x16/apps]\nSize=16\nContext=Applications\n\n\
+             [16x16/status]\nSize=16\nContext=Status\n",
+            &["16x16/status/b.svg", "16x16/apps/b.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        assert_eq!(loader.load_icon("b").unwrap().file_for_size(16).icon_type(), IconFileType::PNG);
+
+        loader.set_context_priority(&["Status"]);
+        let file = loader.load_icon("b").unwrap().file_for_size(16).clone();
+        assert_eq!(file.context(), Some("Status"));
+        assert_eq!(file.icon_type(), IconFileType::SVG);
+    }
+
+    #[cfg(feature = "embed")]
+    #[test]
+    fn test_from_embedded() {
+        use include_dir::{Dir, DirEntry, File};
+
+        static ICONS: Dir = Dir::new(
+            "",
+            &[DirEntry::Dir(Dir::new(
+                "embedded",
+                &[
+                    DirEntry::File(File::new(
+                        "embedded/index.theme",
+                        b"[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n",
+                    )),
+                    DirEntry::Dir(Dir::new(
+                        "embedded/16x16",
+                        &[DirEntry::Dir(Dir::new(
+                            "embedded/16x16/apps",
+                            &[DirEntry::File(File::new("embedded/16x16/apps/icon.png", b"png"))],
+                        ))],
+                    )),
+                ],
+            ))],
+        );
+
+        let mut loader = IconLoader::from_embedded(&ICONS);
+        loader.set_theme_name_provider("embedded");
+        loader.update_theme_name().unwrap();
+
+        let icon = loader.load_icon("icon").unwrap();
+        let file = icon.file_for_size(16);
+
+        assert_eq!(icon.theme_name(), "embedded");
+        assert_eq!(file.path(), None);
+        assert_eq!(file.read().unwrap(), b"png");
+        assert!(loader.load_icon("missing").is_none());
+    }
+
+    #[test]
+    fn test_icon_file_sources() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("app.png");
+        fs::write(&path, "on disk").unwrap();
+
+        let dir_info = Arc::new(IconDir::new("16x16/apps".into(), &Default::default()));
+        let file = IconFile::new(dir_info.clone(), path.clone(), IconFileType::PNG);
+        let bytes = IconFile::from_bytes(dir_info, &b"in memory"[..], IconFileType::PNG);
+
+        assert_eq!(file.path(), Some(path.as_path()));
+        assert_eq!(file.read().unwrap(), b"on disk");
+        assert_eq!(bytes.path(), None);
+        assert_eq!(bytes.read().unwrap(), b"in memory");
+    }
+
+    #[test]
+    fn test_load_icon_reporting_skipped() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/app.xpm"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        let (icon, skipped) = loader.load_icon_reporting_skipped("app");
+        assert_eq!(icon.unwrap().files()[0].icon_type(), IconFileType::XPM);
+        assert!(skipped.is_empty());
+
+        loader.set_allowed_file_types(&[IconFileType::PNG, IconFileType::SVG]);
+        let (icon, skipped) = loader.load_icon_reporting_skipped("app");
+        assert!(icon.is_none());
+        assert_eq!(skipped, [IconFileType::XPM]);
+    }
+
+    #[test]
+    fn test_allowed_file_types() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nDirectories=16x16/apps,scalable/apps\n\n\
+             [16x16/apps]\nSize=16\n\n[scalable/apps]\nSize=16\nType=Scalable\n",
+            &["16x16/apps/app.png", "scalable/apps/app.svg", "16x16/apps/raster.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        assert_eq!(loader.load_icon("app").unwrap().files().len(), 2);
+        assert!(loader.load_icon("raster").is_some());
+
+        loader.set_allowed_file_types(&[IconFileType::SVG]);
+        let icon = loader.load_icon("app").unwrap();
+        assert_eq!(icon.files().len(), 1);
+        assert_eq!(icon.files()[0].icon_type(), IconFileType::SVG);
+        assert!(loader.load_icon("raster").is_none());
+
+        loader.set_case_insensitive_icon_names(true);
+        let icon = loader.load_icon("APP").unwrap();
+        assert_eq!(icon.files().len(), 1);
+        assert_eq!(icon.files()[0].icon_type(), IconFileType::SVG);
+    }
+
+    #[cfg(feature = "zip")]
+    #[test]
+    fn test_add_zip_theme() {
+        use std::io::{Cursor, Write};
+
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let files: [(&str, &[u8]); 2] = [
+            (
+                "zipped/index.theme",
+                b"[Icon Theme]\nInherits=parent\n\n[16x16/apps]\nSize=16\n",
+            ),
+            ("zipped/16x16/apps/app.png", b"png"),
+        ];
+
+        for (name, data) in files {
+            writer
+                .start_file(name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+
+        let archive = writer.finish().unwrap();
+
+        let loader = IconLoader::new("zipped", "zipped");
+        loader.add_zip_theme("zipped", archive).unwrap();
+
+        let icon = loader.load_icon("app").unwrap();
+        assert_eq!(icon.theme_name(), "zipped");
+        assert_eq!(icon.files()[0].path(), None);
+        assert_eq!(icon.files()[0].read().unwrap(), b"png");
+        assert_eq!(loader.theme_parents("zipped"), ["parent", "hicolor"]);
+
+        assert!(matches!(
+            loader.add_zip_theme("broken", Cursor::new(b"not a zip archive")),
+            Err(Error::Archive { .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_mime_icon() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nDirectories=16x16/mimetypes\n\n[16x16/mimetypes]\nSize=16\n",
+            &[
+                "16x16/mimetypes/text-x-python.png",
+                "16x16/mimetypes/text-plain.png",
+                "16x16/mimetypes/application-x-generic.png",
+            ],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        let icon_name = |mime: &str| {
+            loader
+                .load_mime_icon(mime)
+                .map(|icon| icon.icon_name().to_string())
+        };
+
+        assert_eq!(icon_name("text/x-python").as_deref(), Some("text-x-python"));
+        assert_eq!(icon_name("text-x-python").as_deref(), Some("text-x-python"));
+        assert_eq!(icon_name("text/x-rust").as_deref(), Some("text-plain"));
+        assert_eq!(icon_name("application/vnd-foo").as_deref(), Some("application-x-generic"));
+        assert_eq!(icon_name("image/png"), None);
+        assert_eq!(icon_name("invalid"), None);
+    }
+
+    #[test]
+    fn test_pointer_theme() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "actual",
+            "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/app.png"],
+        );
+        create_theme(root.path(), "default", "[Icon Theme]\nInherits=actual\n", &[]);
+
+        let mut loader = IconLoader::new("default", "default");
+        loader.set_search_paths([root.path()]);
+
+        assert!(loader.theme_exists("default"));
+        assert_eq!(loader.theme_parents("default"), ["actual", "hicolor"]);
+        assert_eq!(loader.try_load_icon("app").unwrap().theme_name(), "actual");
+    }
+
+    #[test]
+    fn test_duplicate_files() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=32x32/apps\n\n[32x32/apps]\nSize=32\nContext=Applications\n";
+        create_theme(first.path(), "test", index, &["32x32/apps/firefox.png"]);
+        create_theme(second.path(), "test", index, &["32x32/apps/firefox.png"]);
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([first.path(), second.path()]);
+
+        let icon = loader.load_icon("firefox").unwrap();
+        assert_eq!(icon.files().len(), 1);
+        assert_eq!(
+            icon.file_for_size(32).path(),
+            Some(&*first.path().join("test/32x32/apps/firefox.png"))
+        );
+
+        let files = vec![icon.files()[0].clone(), icon.files()[0].clone()];
+        let icon = Icon::from_files("firefox", "test", files).unwrap();
+        assert_eq!(icon.files().len(), 1);
+    }
+
+    #[test]
+    fn test_fixed_and_scalable_files_of_same_size() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nDirectories=48x48/apps,scalable/apps\n\n\
+             [48x48/apps]\nSize=48\nType=Fixed\n\n\
+             [scalable/apps]\nSize=48\nMaxSize=512\nType=Scalable\n",
+            &["48x48/apps/a.svg", "scalable/apps/a.svg"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        let icon = loader.load_icon("a").unwrap();
+        assert_eq!(icon.files().len(), 2);
+        assert_eq!(icon.file_for_size(48).dir_info().path().to_str(), Some("48x48/apps"));
+        assert_eq!(icon.file_for_size(256).dir_info().path().to_str(), Some("scalable/apps"));
+    }
+
+    #[test]
+    fn test_fallback_icon() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/app.png", "16x16/apps/image-missing.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        assert!(loader.load_icon_or_fallback("other").is_none());
+
+        loader.set_fallback_icon("image-missing");
+        let icon_name = |icon_name| {
+            loader
+                .load_icon_or_fallback(icon_name)
+                .map(|icon| icon.icon_name().to_string())
+        };
+        assert_eq!(icon_name("app").as_deref(), Some("app"));
+        assert_eq!(icon_name("other").as_deref(), Some("image-missing"));
+
+        loader.set_fallback_icon("also-missing");
+        assert!(loader.load_icon_or_fallback("other").is_none());
+    }
+
+    #[test]
+    fn test_load_first() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/first.png", "16x16/apps/second.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        let icon_name = |icon_names: &[&str]| {
+            loader
+                .load_first(icon_names)
+                .map(|icon| icon.icon_name().to_string())
+        };
+
+        assert_eq!(icon_name(&["first", "second"]).as_deref(), Some("first"));
+        assert_eq!(icon_name(&["other", "second", "first"]).as_deref(), Some("second"));
+        assert!(icon_name(&["other", "another"]).is_none());
+        assert!(icon_name(&[]).is_none());
+    }
+
+    #[test]
+    fn test_load_icon_or_missing() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(
+            root.path(),
+            "test",
+            index,
+            &["16x16/apps/app.png", "16x16/apps/unknown.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        let icon_name = |loader: &IconLoader, icon_name| {
+            loader
+                .load_icon_or_missing(icon_name)
+                .map(|icon| icon.icon_name().to_string())
+        };
+
+        assert_eq!(icon_name(&loader, "app").as_deref(), Some("app"));
+        assert_eq!(icon_name(&loader, "other").as_deref(), Some("unknown"));
+
+        create_theme(root.path(), "test", index, &["16x16/apps/image-missing.png"]);
+        loader.clear_cache();
+        assert_eq!(icon_name(&loader, "other").as_deref(), Some("image-missing"));
+
+        let empty = tempfile::tempdir().unwrap();
+        create_theme(empty.path(), "test", index, &[]);
+        loader.set_search_paths([empty.path()]);
+        assert!(icon_name(&loader, "other").is_none());
+    }
+
+    #[test]
+    fn test_hidpi_theme() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "hidpi",
+            "[Icon Theme]\nDirectories=48x48/apps,22x22@2/apps,32x32@2/apps,scalable@2/apps\n\n\
+             [48x48/apps]\nSize=48\nType=Fixed\n\n\
+             [22x22@2/apps]\nSize=22\nScale=2\nType=Threshold\n\n\
+             [32x32@2/apps]\nSize=32\nScale=2\nType=Fixed\n\n\
+             [scalable@2/apps]\nSize=64\nMinSize=40\nMaxSize=128\nScale=2\nType=Scalable\n",
+            &[
+                "48x48/apps/app.png",
+                "22x22@2/apps/app.png",
+                "32x32@2/apps/app.png",
+                "scalable@2/apps/app.svg",
+            ],
+        );
+
+        let mut loader = IconLoader::new("hidpi", "hidpi");
+        loader.set_search_paths([root.path()]);
+
+        let icon = loader.load_icon("app").unwrap();
+        let path_for = |size, scale| {
+            icon.file_for_size_scaled(size, scale)
+                .dir_info()
+                .path()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        // Directories of the requested scale are preferred over scale 1 directories of the same pixel size.
+        assert_eq!(path_for(24, 2), "22x22@2/apps");
+        assert_eq!(path_for(32, 2), "32x32@2/apps");
+        assert_eq!(path_for(48, 2), "scalable@2/apps");
+        assert_eq!(path_for(26, 2), "22x22@2/apps");
+        assert_eq!(path_for(48, 1), "48x48/apps");
+    }
+
+    #[test]
+    fn test_prepend_and_append_search_path() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let bundled = tempfile::tempdir().unwrap();
+
+        let mut loader = IconLoader::new("x", "x");
+        loader.set_search_paths([first.path()]);
+        loader.append_search_path(second.path());
+        loader.prepend_search_path(bundled.path());
+        assert_eq!(
+            *loader.search_paths(),
+            [bundled.path(), first.path(), second.path()]
+        );
+
+        let mut loader = IconLoader::new("x", "x");
+        let system_paths = loader.search_paths().into_owned();
+        loader.prepend_search_path(bundled.path());
+        assert_eq!(loader.search_paths()[0], bundled.path());
+        assert_eq!(loader.search_paths()[1..], system_paths[..]);
+    }
+
+    #[test]
+    fn test_icon_file_type_parsing() {
+        assert_eq!(IconFileType::from_extension("png"), Some(IconFileType::PNG));
+        assert_eq!(IconFileType::from_extension("SVG"), Some(IconFileType::SVG));
+        assert_eq!(IconFileType::from_extension("Xpm"), Some(IconFileType::XPM));
+        assert_eq!(IconFileType::from_extension("ico"), None);
+
+        assert_eq!(
+            IconFileType::from_path(Path::new("/usr/share/pixmaps/app.PNG")),
+            Some(IconFileType::PNG)
+        );
+        assert_eq!(IconFileType::from_path(Path::new("icon.svg")), Some(IconFileType::SVG));
+        assert_eq!(IconFileType::from_path(Path::new("icon")), None);
+    }
+
+    #[test]
+    fn test_load_icon_path() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/app.png"],
+        );
+        let path = root.path().join("custom.svg");
+        fs::write(&path, "").unwrap();
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        let icon = loader.load_icon_path(path.to_str().unwrap()).unwrap();
+        assert_eq!(icon.icon_name(), "custom");
+        assert_eq!(icon.theme_name(), "file");
+        assert_eq!(icon.file_for_size(16).path(), Some(path.as_path()));
+        assert_eq!(icon.file_for_size(16).icon_type(), IconFileType::SVG);
+
+        assert_eq!(loader.load_icon_path("app").unwrap().theme_name(), "test");
+        assert!(loader
+            .load_icon_path(root.path().join("missing.png").to_str().unwrap())
+            .is_none());
+    }
+
+    #[test]
+    fn test_overlapping_thresholds() {
+        let icon = icon_from_index(
+            "[16x16/apps]\nSize=16\nThreshold=4\nType=Threshold\n\n\
+             [22x22/apps]\nSize=22\nThreshold=2\nType=Threshold\n",
+        );
+
+        let path_for = |size| {
+            icon.file_for_size(size)
+                .dir_info()
+                .path()
+                .to_str()
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(path_for(18), "16x16/apps");
+        assert_eq!(path_for(20), "22x22/apps");
+        assert_eq!(path_for(21), "22x22/apps");
+        assert_eq!(path_for(12), "16x16/apps");
+    }
+
+    #[test]
+    fn test_file_for_size_by() {
+        let dir_info = |index: &str| icon_from_index(index).files()[0].dir_info().clone();
+        let fixed = Arc::new(dir_info("[32x32/apps]\nSize=32\nType=Fixed\n"));
+        let scalable = Arc::new(dir_info(
+            "[scalable/apps]\nSize=32\nMinSize=16\nMaxSize=256\nType=Scalable\n",
+        ));
+        let fixed_16 = Arc::new(dir_info("[16x16/apps]\nSize=16\nType=Fixed\n"));
+
+        let files = vec![
+            IconFile::new(fixed, "32x32/apps/app.png".into(), IconFileType::PNG),
+            IconFile::new(scalable, "scalable/apps/app.svg".into(), IconFileType::SVG),
+            IconFile::new(fixed_16, "16x16/apps/app.svg".into(), IconFileType::SVG),
+        ];
+        let icon = Icon::from_files("app", "test", files).unwrap();
+        let prefer_svg = |a: &IconFile, b: &IconFile| {
+            (b.icon_type() == IconFileType::SVG).cmp(&(a.icon_type() == IconFileType::SVG))
+        };
+
+        assert_eq!(icon.file_for_size(32).icon_type(), IconFileType::PNG);
+        assert_eq!(
+            icon.file_for_size_by(32, prefer_svg).unwrap().path(),
+            Some(Path::new("scalable/apps/app.svg"))
+        );
+        // The comparator does not override a better size fit.
+        assert_eq!(
+            icon.file_for_size_by(24, |_, _| std::cmp::Ordering::Equal)
+                .unwrap()
+                .path(),
+            Some(Path::new("scalable/apps/app.svg"))
+        );
+    }
+
+    #[test]
+    fn test_theme_root() {
+        let user = tempfile::tempdir().unwrap();
+        let system = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(user.path(), "test", index, &["16x16/apps/own.png"]);
+        create_theme(system.path(), "test", index, &["16x16/apps/own.png", "16x16/apps/other.png"]);
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([user.path(), system.path()]);
+
+        let theme_root = |icon_name| {
+            let icon = loader.load_icon(icon_name).unwrap();
+            icon.file_for_size(16).theme_root().map(Path::to_path_buf)
+        };
+        assert_eq!(theme_root("own").as_deref(), Some(user.path()));
+        assert_eq!(theme_root("other").as_deref(), Some(system.path()));
+
+        let dir_info = Arc::new(IconDir::new("16x16/apps".into(), &Default::default()));
+        let bytes = IconFile::from_bytes(dir_info, &b""[..], IconFileType::PNG);
+        assert_eq!(bytes.theme_root(), None);
+    }
+
+    #[test]
+    fn test_theme_name_changed() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "first", index, &[]);
+        create_theme(root.path(), "second", index, &[]);
+
+        let provided = Arc::new(Mutex::new(String::from("first")));
+        let provider = {
+            let provided = provided.clone();
+            ThemeNameProvider::custom(move || Ok::<_, std::io::Error>(provided.lock().unwrap().clone()))
+        };
+
+        let mut loader = IconLoader::new("x", "x");
+        loader.set_search_paths([root.path()]);
+        loader.set_theme_name_provider(provider);
+        loader.update_theme_name().unwrap();
+        assert!(!loader.theme_name_changed().unwrap());
+
+        *provided.lock().unwrap() = String::from("second");
+        assert!(loader.theme_name_changed().unwrap());
+        assert_eq!(loader.theme_name(), "first");
+
+        loader.update_theme_name().unwrap();
+        assert!(!loader.theme_name_changed().unwrap());
+
+        *provided.lock().unwrap() = String::from("missing");
+        loader.set_theme_not_found_policy(ThemeNotFoundPolicy::FallbackToBase);
+        loader.update_theme_name().unwrap();
+        assert_eq!(loader.theme_name(), "hicolor");
+        assert!(!loader.theme_name_changed().unwrap());
+
+        loader.set_theme_name_provider("other");
+        assert!(!loader.theme_name_changed().unwrap());
+    }
+
+    /// Builds an `icon-theme.cache` file with a single hash bucket, listing each icon
+    /// in the given directories with the given file type flags.
+    #[cfg(feature = "gtk-cache")]
+    fn icon_theme_cache(dirs: &[&str], icons: &[(&str, &[(u16, u16)])]) -> Vec<u8> {
+        fn set_u32(data: &mut [u8], offset: usize, value: usize) {
+            data[offset..offset + 4].copy_from_slice(&(value as u32).to_be_bytes());
+        }
+
+        fn push_str(data: &mut Vec<u8>, string: &str) {
+            data.extend(string.bytes().chain([0]));
+
+            while !data.len().is_multiple_of(4) {
+                data.push(0);
+            }
+        }
+
+        let mut data = vec![0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+        let directory_list_offset = data.len();
+        set_u32(&mut data, 8, directory_list_offset);
+        data.resize(directory_list_offset + 4 + 4 * dirs.len(), 0);
+        set_u32(&mut data, directory_list_offset, dirs.len());
+
+        for (index, dir) in dirs.iter().enumerate() {
+            let offset = data.len();
+            set_u32(&mut data, directory_list_offset + 4 + 4 * index, offset);
+            push_str(&mut data, dir);
+        }
+
+        let hash_offset = data.len();
+        set_u32(&mut data, 4, hash_offset);
+        data.resize(hash_offset + 8, 0);
+        set_u32(&mut data, hash_offset, 1);
+
+        let mut chain_slot = hash_offset + 4;
+
+        for (name, images) in icons {
+            let icon_offset = data.len();
+            set_u32(&mut data, chain_slot, icon_offset);
+            chain_slot = icon_offset;
+            data.resize(icon_offset + 12, 0);
+
+            let name_offset = data.len();
+            set_u32(&mut data, icon_offset + 4, name_offset);
+            push_str(&mut data, name);
+
+            let image_list_offset = data.len();
+            set_u32(&mut data, icon_offset + 8, image_list_offset);
+            data.extend((images.len() as u32).to_be_bytes());
+
+            for (dir_index, flags) in images.iter() {
+                data.extend(dir_index.to_be_bytes());
+                data.extend(flags.to_be_bytes());
+                data.extend([0; 4]);
+            }
+        }
+
+        set_u32(&mut data, chain_slot, 0xffff_ffff);
+
+        data
+    }
+
+    #[cfg(feature = "gtk-cache")]
+    #[test]
+    fn test_icon_theme_cache() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "cached",
+            "[Icon Theme]\nDirectories=16x16/apps,32x32/apps\n\n\
+             [16x16/apps]\nSize=16\n\n[32x32/apps]\nSize=32\n",
+            &["16x16/apps/app.png", "32x32/apps/app.png"],
+        );
+
+        // The cache only knows the PNG file in 16x16/apps.
+        let cache_path = root.path().join("cached/icon-theme.cache");
+        let cache = icon_theme_cache(&["16x16/apps", "32x32/apps"], &[("app", &[(0, 4)])]);
+        fs::write(&cache_path, cache).unwrap();
+
+        let load_files = || {
+            let mut loader = IconLoader::new("cached", "cached");
+            loader.set_search_paths([root.path()]);
+            loader.load_icon("app").map_or(0, |icon| icon.files().len())
+        };
+        assert_eq!(load_files(), 1);
+
+        // Outdated caches are ignored.
+        fs::File::options()
+            .write(true)
+            .open(&cache_path)
+            .unwrap()
+            .set_modified(std::time::UNIX_EPOCH)
+            .unwrap();
+        assert_eq!(load_files(), 2);
+    }
+
+    #[test]
+    fn test_into_parts() {
+        let icon = icon_from_index("[16x16/apps]\nSize=16\n\n[32x32/apps]\nSize=32\n");
+        let files = icon.files().to_vec();
+
+        assert_eq!(icon.clone().into_files(), files);
+
+        let (icon_name, theme_name, parts_files) = icon.clone().into_parts();
+        assert_eq!(icon_name, icon.icon_name());
+        assert_eq!(theme_name, icon.theme_name());
+        assert_eq!(parts_files, files);
+    }
+
+    #[test]
+    fn test_walk_theme() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nDirectories=16x16/apps,32x32/apps\n\n\
+             [16x16/apps]\nSize=16\n\n[32x32/apps]\nSize=32\n",
+            &["16x16/apps/app.png", "32x32/apps/app.svg", "32x32/apps/other.png"],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+
+        let mut names = Vec::new();
+        let result = loader.walk_theme("test", |icon_name, _| {
+            names.push(icon_name.to_string());
+            None::<()>
+        });
+        names.sort_unstable();
+        assert_eq!(result, None);
+        assert_eq!(names, ["app", "app", "other"]);
+
+        let found = loader.walk_theme("test", |icon_name, file| {
+            (icon_name == "other").then(|| file.icon_type())
+        });
+        assert_eq!(found, Some(IconFileType::PNG));
+
+        loader.set_allowed_file_types(&[IconFileType::SVG]);
+        let mut count = 0;
+        loader.walk_theme("test", |_, _| {
+            count += 1;
+            None::<()>
+        });
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_icon_display_and_debug() {
+        let icon = icon_from_index(
+            "[32x32/apps]\nSize=32\n\n[16x16/apps]\nSize=16\n\n[16x16@2/apps]\nSize=16\nScale=2\n\n\
+             [scalable/apps]\nSize=16\nType=Scalable\n",
+        );
+
+        assert_eq!(icon.to_string(), "icon @ test (4 files)");
+        assert_eq!(
+            format!("{:?}", icon),
+            r#"Icon { icon_name: "icon", theme_name: "test", sizes: [16, 16@2, 32, scalable] }"#
+        );
+    }
+
+    #[test]
+    fn test_prefer_scalable_for_large_sizes() {
+        let icon = icon_from_index(
+            "[256x256/apps]\nSize=256\nType=Fixed\n\n[scalable/apps]\nSize=48\nType=Scalable\n",
+        );
+
+        let file = icon.file_for_size(512);
+        assert!(file.is_scalable());
+        assert_eq!(file.icon_type(), IconFileType::SVG);
+
+        assert!(!icon.file_for_size(256).is_scalable());
+        assert!(!icon.file_for_size(200).is_scalable());
+        assert!(icon.file_for_size_scaled(256, 2).is_scalable());
+    }
+
+    #[test]
+    fn test_iter_entries_scaled() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "test",
+            "[Icon Theme]\nName=Test\n\n[16x16/apps]\nSize=16\n\n\
+             [16x16@2/apps]\nSize=16\nScale=2\n\n[32x32@2/apps]\nSize=32\nScale=2\n",
+            &[
+                "16x16/apps/a.png",
+                "16x16@2/apps/a.png",
+                "16x16@2/apps/b.svg",
+                "32x32@2/apps/c.png",
+            ],
+        );
+
+        let mut loader = IconLoader::new("test", "test");
+        loader.set_search_paths([root.path()]);
+        let chain = loader.theme_chain("test").unwrap();
+        let theme = &chain.themes()[0];
+
+        let entries = |scale: u16| {
+            let mut entries: Vec<(String, u16)> = theme
+                .iter_entries_scaled(scale)
+                .map(|(name, file)| (name, file.scale()))
+                .collect();
+            entries.sort();
+            entries
+        };
+
+        assert_eq!(entries(1), [("a".to_string(), 1)]);
+        assert_eq!(
+            entries(2),
+            [("a".to_string(), 2), ("b".to_string(), 2), ("c".to_string(), 2)]
+        );
+        assert!(entries(3).is_empty());
+    }
+
+    #[test]
+    fn test_size_scale_pairs() {
+        let icon = icon_from_index(
+            "[32x32/apps]\nSize=32\n\n[16x16/apps]\nSize=16\n\n[16x16/mimetypes]\nSize=16\n\n\
+             [16x16@2/apps]\nSize=16\nScale=2\n\n[16x16@2/mimetypes]\nSize=16\nScale=2\n",
+        );
+
+        assert_eq!(icon.size_scale_pairs(), [(16, 1), (16, 2), (32, 1)]);
+    }
+
+    #[test]
+    fn test_available_sizes() {
+        let icon = icon_from_index(
+            "[48x48/apps]\nSize=48\n\n[16x16/apps]\nSize=16\n\n[16x16@2/apps]\nSize=16\nScale=2\n\n\
+             [24x24@3/apps]\nSize=24\nScale=3\n",
+        );
+
+        assert_eq!(icon.available_sizes(), [16, 24, 48]);
+        assert_eq!(icon.available_scales(), [1, 2, 3]);
+        assert!(!icon.has_scalable());
+
+        let icon = icon_from_index("[32x32/apps]\nSize=32\n\n[scalable/apps]\nSize=64\nType=Scalable\n");
+
+        assert_eq!(icon.available_sizes(), [32, 64]);
+        assert_eq!(icon.available_scales(), [1]);
+        assert!(icon.has_scalable());
+    }
+
+    #[test]
+    fn test_implicit_fallback_theme() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "app", index, &["16x16/apps/app.png"]);
+        create_theme(root.path(), "hicolor", index, &["16x16/apps/system.png"]);
+        create_theme(root.path(), "base", index, &["16x16/apps/base.png"]);
+
+        let mut loader = IconLoader::new("app", "app");
+        loader.set_search_paths([root.path()]);
+        assert_eq!(loader.load_icon("system").unwrap().theme_name(), "hicolor");
+
+        loader.set_implicit_fallback_theme(None);
+        assert!(loader.load_icon("system").is_none());
+        assert!(loader.load_icon("app").is_some());
+        assert_eq!(loader.resolved_theme_chain("app"), ["app"]);
+
+        loader.set_implicit_fallback_theme(Some(String::from("base")));
+        assert_eq!(loader.load_icon("base").unwrap().theme_name(), "base");
+        assert!(loader.load_icon("system").is_none());
+    }
+
+    #[test]
+    fn test_icon_loader_builder() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "theme", index, &["16x16/apps/app.png", "16x16/apps/app.svg"]);
+        create_theme(root.path(), "fallback", index, &["16x16/apps/other.png"]);
+
+        let loader = IconLoader::builder()
+            .theme_name_provider("theme")
+            .fallback_theme_name("fallback")
+            .search_paths([root.path()])
+            .allowed_file_types(&[IconFileType::SVG])
+            .build()
+            .unwrap();
+
+        assert_eq!(loader.theme_name(), "theme");
+        assert_eq!(loader.fallback_theme_name(), "fallback");
+        assert_eq!(loader.load_icon("app").unwrap().files().len(), 1);
+        assert!(loader.load_icon("other").is_none());
+
+        let result = IconLoaderBuilder::new()
+            .theme_name_provider("missing")
+            .search_paths([root.path()])
+            .build();
+        assert!(matches!(result, Err(Error::ThemeNotFound { .. })));
+
+        let loader = IconLoaderBuilder::new()
+            .theme_name_provider("missing")
+            .theme_not_found_policy(ThemeNotFoundPolicy::FallbackToBase)
+            .search_paths([root.path()])
+            .build()
+            .unwrap();
+        assert_eq!(loader.theme_name(), "hicolor");
+    }
+
+    #[test]
+    fn test_cached_custom_provider() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        let provider = ThemeNameProvider::cached_custom(|| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, std::io::Error>("cached")
+        });
+        let mut loader = IconLoader::new_hicolor();
+        loader.set_theme_name_provider(provider);
+        loader.set_theme_not_found_policy(ThemeNotFoundPolicy::FallbackToBase);
+
+        loader.update_theme_name().unwrap();
+        loader.update_theme_name().unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+
+        loader.theme_name_provider().invalidate();
+        loader.update_theme_name().unwrap();
+        assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+        assert_ne!(loader.theme_name_provider(), loader.theme_name_provider());
+    }
+
+    #[test]
+    fn test_icon_from_application_data() {
+        let dir = |size| Arc::new(IconDir::with_size(format!("{0}x{0}/apps", size), size));
+        let scalable = Arc::new(
+            IconDir::with_size("scalable/apps", 48)
+                .with_size_type(IconSizeType::Scalable)
+                .with_size_range(64, 512)
+                .with_context("Apps"),
+        );
+        let files = vec![
+            IconFile::from_bytes(dir(16), &b"16"[..], IconFileType::PNG),
+            IconFile::from_bytes(dir(32), &b"32"[..], IconFileType::PNG),
+            IconFile::from_bytes(scalable, &b"svg"[..], IconFileType::SVG),
+        ];
+
+        let icon = Icon::from_files("app", "memory", files).unwrap();
+        assert_eq!(icon.file_for_size(16).read().unwrap(), b"16");
+        assert_eq!(icon.file_for_size(30).read().unwrap(), b"32");
+        assert_eq!(icon.file_for_size(256).read().unwrap(), b"svg");
+        assert_eq!(icon.file_for_size(256).context(), Some("Apps"));
+
+        assert!(Icon::from_files("app", "memory", Vec::new()).is_none());
+        assert!(Icon::from_files("", "memory", icon.into_files()).is_none());
+    }
+
+    #[test]
+    fn test_file_for_exact_size() {
+        let icon = icon_from_index(
+            "[16x16/apps]\nSize=16\n\n[32x32/apps]\nSize=32\n\n[32x32@2/apps]\nSize=32\nScale=2\n",
+        );
+
+        assert_eq!(icon.file_for_exact_size(16, 1).unwrap().size(), 16);
+        assert_eq!(icon.file_for_exact_size(32, 2).unwrap().scale(), 2);
+        assert!(icon.file_for_exact_size(24, 1).is_none());
+        assert!(icon.file_for_exact_size(17, 1).is_none());
+        assert!(icon.file_for_exact_size(16, 2).is_none());
+
+        let scalable =
+            icon_from_index("[scalable/apps]\nSize=48\nType=Scalable\nMinSize=16\nMaxSize=256\n");
+        assert!(scalable.file_for_exact_size(64, 1).is_some());
+        assert!(scalable.file_for_exact_size(512, 1).is_none());
+    }
+
+    #[test]
+    fn test_set_theme_name() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "theme", index, &["16x16/apps/app.png"]);
+
+        let mut loader = IconLoader::new("other", "other");
+        loader.set_search_paths([root.path()]);
+
+        assert!(matches!(
+            loader.set_theme_name("missing"),
+            Err(Error::ThemeNotFound { .. })
+        ));
+        assert_eq!(loader.theme_name(), "other");
+
+        loader.set_theme_name("theme").unwrap();
+        assert_eq!(loader.theme_name(), "theme");
+        assert_eq!(loader.load_icon("app").unwrap().theme_name(), "theme");
+
+        loader.update_theme_name().unwrap();
+        assert_eq!(loader.theme_name(), "theme");
+    }
+
+    #[test]
+    fn test_theme_chain() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "theme",
+            "[Icon Theme]\nInherits=base\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n",
+            &["16x16/apps/app.png"],
+        );
+
+        let mut loader = IconLoader::new("theme", "theme");
+        loader.set_search_paths([root.path()]);
+
+        let chain = loader.theme_chain("theme").unwrap();
+        assert_eq!(chain.name(), "theme");
+        assert_eq!(chain.parent_names(), ["base", "hicolor"]);
+        assert_eq!(chain.themes().len(), 1);
+        assert_eq!(chain.themes()[0].dirs().len(), 1);
+        assert_eq!(chain.themes()[0].directories(), chain.themes()[0].dirs());
+        assert!(Arc::ptr_eq(&chain, &loader.theme_chain("theme").unwrap()));
+
+        assert!(loader.theme_chain("missing").is_none());
+        assert!(loader.theme_chain("").is_none());
+    }
+
+    #[test]
+    fn test_inspect_theme_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "theme",
+            "[Icon Theme]\nDirectories=16x16/apps,scalable/mimetypes\n\n\
+             [16x16/apps]\nSize=16\nContext=Applications\n\n\
+             [scalable/mimetypes]\nSize=64\nType=Scalable\nContext=MimeTypes\n",
+            &[],
+        );
+
+        let mut loader = IconLoader::new("theme", "theme");
+        loader.set_search_paths([root.path()]);
+
+        let chain = loader.theme_chain("theme").unwrap();
+        let theme = &chain.themes()[0];
+        let dirs: Vec<_> = theme
+            .dirs()
+            .iter()
+            .map(|dir| (dir.size(), dir.size_type(), dir.context()))
+            .collect();
+
+        assert_eq!(theme.content_dir(), root.path().join("theme"));
+        assert_eq!(
+            dirs,
+            [
+                (16, IconSizeType::Threshold, Some("Applications")),
+                (64, IconSizeType::Scalable, Some("MimeTypes")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_icons() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(
+            root.path(),
+            "theme",
+            index,
+            &[
+                "16x16/apps/edit-copy.png",
+                "16x16/apps/edit-copy.svg",
+                "16x16/apps/edit.png",
+                "16x16/apps/document-edit.png",
+                "16x16/apps/credit.png",
+            ],
+        );
+        let fallback_files = ["16x16/apps/editor.png", "16x16/apps/other.png"];
+        create_theme(root.path(), "fallback", index, &fallback_files);
+
+        let mut loader = IconLoader::new("theme", "fallback");
+        loader.set_search_paths([root.path()]);
+
+        assert_eq!(
+            loader.search_icons("EDIT", 10),
+            ["edit", "editor", "edit-copy", "document-edit", "credit"]
+        );
+        assert_eq!(loader.search_icons("edit", 2), ["edit", "editor"]);
+        assert!(loader.search_icons("missing", 10).is_empty());
+    }
+
+    #[test]
+    fn test_declared_directories() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "theme",
+            "[Icon Theme]\nDirectories=32x32/apps,missing/apps\nScaledDirectories=16x16@2/apps\n\n\
+             [16x16/apps]\nSize=16\n\n[32x32/apps]\nSize=32\n\n[16x16@2/apps]\nSize=16\nScale=2\n",
+            &["16x16/apps/stray.png", "32x32/apps/app.png", "16x16@2/apps/app.png"],
+        );
+        create_theme(
+            root.path(),
+            "hidden",
+            "[Icon Theme]\nHidden=true\n\n[16x16/apps]\nSize=16\n",
+            &[],
+        );
+
+        let mut loader = IconLoader::new("theme", "theme");
+        loader.set_search_paths([root.path()]);
+
+        let chain = loader.theme_chain("theme").unwrap();
+        let dirs: Vec<_> = chain.themes()[0].dirs().iter().map(|dir| dir.path()).collect();
+        assert_eq!(dirs, [Path::new("32x32/apps"), Path::new("16x16@2/apps")]);
+        assert!(loader.load_icon("stray").is_none());
+        assert_eq!(loader.load_icon("app").unwrap().files().len(), 2);
+
+        assert!(loader.theme_chain("hidden").unwrap().themes()[0].is_hidden());
+        assert_eq!(loader.available_themes(false), ["theme"]);
+    }
+
+    #[test]
+    fn test_lenient_parsing() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "broken",
+            "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n[broken\n",
+            &["16x16/apps/app.png"],
+        );
+
+        let mut loader = IconLoader::new("broken", "broken");
+        loader.set_search_paths([root.path()]);
+        assert!(loader.load_icon("app").is_none());
+
+        loader.set_lenient_parsing(true);
+        assert_eq!(loader.load_icon("app").unwrap().theme_name(), "broken");
+        assert_eq!(loader.available_themes(false), ["broken"]);
+    }
+
+    #[test]
+    fn test_load_icon_checked() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "theme", index, &["16x16/apps/app.png"]);
+
+        let mut loader = IconLoader::new("theme", "theme");
+        loader.set_search_paths([root.path()]);
+        assert_eq!(loader.load_icon_checked("app").unwrap().unwrap().theme_name(), "theme");
+        assert!(loader.load_icon_checked("missing").unwrap().is_none());
+
+        loader.set_theme_name_provider(|| Err::<String, _>(std::io::Error::other("broken config")));
+        assert!(loader.load_icon("app").is_some());
+        assert!(matches!(
+            loader.load_icon_checked("app"),
+            Err(Error::ThemeNameProvider {
+                source: ProviderError::Custom { .. }
+            })
+        ));
+
+        loader.set_theme_name_provider("missing");
+        assert!(matches!(
+            loader.load_icon_checked("app"),
+            Err(Error::ThemeNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_file_for_size_fractional() {
+        let icon = icon_from_index(
+            "[32x32/apps]\nSize=32\nType=Fixed\n\n[48x48/apps]\nSize=48\nType=Fixed\n\n\
+             [32x32@2/apps]\nSize=32\nScale=2\nType=Fixed\n",
+        );
+
+        assert_eq!(icon.file_for_size_fractional(32, 1.0).size(), 32);
+        assert_eq!(icon.file_for_size_fractional(32, 1.5).size(), 48);
+        assert_eq!(icon.file_for_size_fractional(32, 2.0).scale(), 2);
+        assert_eq!(icon.file_for_size_fractional(32, f32::NAN).size(), 32);
+        assert_eq!(icon.file_for_size_fractional(32, -1.0).size(), 32);
+    }
+
+    #[test]
+    fn test_reload() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "first", index, &[]);
+
+        let config = Arc::new(Mutex::new(String::from("first")));
+        let provider_config = config.clone();
+        let mut loader = IconLoader::builder()
+            .theme_name_provider(ThemeNameProvider::cached_custom(move || {
+                Ok::<_, std::io::Error>(provider_config.lock().unwrap().clone())
+            }))
+            .search_paths([root.path()])
+            .build()
+            .unwrap();
+        assert!(loader.load_icon("app").is_none());
+
+        // Without reloading, the old theme name and the icon cached as missing are used.
+        create_theme(root.path(), "second", index, &["16x16/apps/app.png"]);
+        create_theme(root.path(), "first", index, &["16x16/apps/app.png"]);
+        *config.lock().unwrap() = String::from("second");
+        assert!(loader.load_icon("app").is_none());
+
+        loader.reload().unwrap();
+        assert_eq!(loader.theme_name(), "second");
+        assert_eq!(loader.load_icon("app").unwrap().theme_name(), "second");
+    }
+
+    #[cfg(feature = "xfce")]
+    #[test]
+    fn test_xfce_provider() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let xsettings = config_dir.path().join("xfce4/xfconf/xfce-perchannel-xml/xsettings.xml");
+        fs::create_dir_all(xsettings.parent().unwrap()).unwrap();
+
+        let provider = || ThemeNameProvider::xfce_with_config_dir(config_dir.path());
+        let theme_name = || {
+            IconLoader::new_from_provider(provider()).map(|loader| loader.theme_name().to_string())
+        };
+
+        fs::write(
+            &xsettings,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<channel name="xsettings" version="1.0">
+  <property name="Net" type="empty">
+    <property name="ThemeName" type="string" value="Greybird"/>
+    <property name="IconThemeName" type="string" value="elementary-xfce"/>
+  </property>
+</channel>
+"#,
+        )
+        .unwrap();
+        assert_eq!(theme_name().unwrap(), "elementary-xfce");
+
+        fs::write(
+            &xsettings,
+            r#"<channel name="xsettings" version="1.0"><property name="Net" type="empty"/></channel>"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            theme_name(),
+            Err(Error::ThemeNameProvider {
+                source: ProviderError::ConfigMissingThemeName
+            })
+        ));
+
+        fs::write(&xsettings, "<channel").unwrap();
+        assert!(matches!(
+            theme_name(),
+            Err(Error::ThemeNameProvider {
+                source: ProviderError::ParseXml { .. }
+            })
+        ));
+
+        fs::write(&xsettings, [0xff, 0xfe]).unwrap();
+        assert!(matches!(
+            theme_name(),
+            Err(Error::ThemeNameProvider {
+                source: ProviderError::ReadConfig { .. }
+            })
+        ));
+    }
+
+    #[cfg(all(feature = "gsettings", unix))]
+    #[test]
+    fn test_gsettings_provider() {
+        use crate::theme_name_provider::gsettings_theme_name;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("gsettings");
+        let write_program = |script: &str| {
+            fs::write(&program, format!("#!/bin/sh\n{}\n", script)).unwrap();
+            fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        };
+
+        write_program("echo \"'Adwaita'\"");
+        assert_eq!(gsettings_theme_name(program.as_ref()).unwrap(), "Adwaita");
+
+        write_program("echo '\"Papirus-Dark\"'");
+        assert_eq!(gsettings_theme_name(program.as_ref()).unwrap(), "Papirus-Dark");
+
+        write_program("echo \"''\"");
+        assert!(matches!(
+            gsettings_theme_name(program.as_ref()),
+            Err(ProviderError::ConfigMissingThemeName)
+        ));
+
+        write_program("echo 'No such schema' >&2; exit 1");
+        assert!(matches!(
+            gsettings_theme_name(program.as_ref()),
+            Err(ProviderError::ConfigNotFound)
+        ));
+
+        assert!(matches!(
+            gsettings_theme_name(dir.path().join("missing").as_ref()),
+            Err(ProviderError::ConfigNotFound)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_same_icon() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(
+            root.path(),
+            "theme",
+            index,
+            &["16x16/apps/utilities-terminal.png", "16x16/apps/other.png"],
+        );
+
+        let apps = root.path().join("theme/16x16/apps");
+        std::os::unix::fs::symlink(apps.join("utilities-terminal.png"), apps.join("gnome-terminal.png"))
+            .unwrap();
+
+        let mut loader = IconLoader::new("theme", "theme");
+        loader.set_search_paths([root.path()]);
+
+        assert!(loader.same_icon("gnome-terminal", "utilities-terminal", 16));
+        assert!(loader.same_icon("other", "other", 16));
+        assert!(!loader.same_icon("gnome-terminal", "other", 16));
+        assert!(!loader.same_icon("gnome-terminal", "missing", 16));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_canonical_path() {
+        let root = tempfile::tempdir().unwrap();
+        let index = "[Icon Theme]\nDirectories=16x16/apps\n\n[16x16/apps]\nSize=16\n";
+        create_theme(root.path(), "theme", index, &["16x16/apps/utilities-terminal.png"]);
+
+        let apps = root.path().join("theme/16x16/apps");
+        std::os::unix::fs::symlink(apps.join("utilities-terminal.png"), apps.join("gnome-terminal.png"))
+            .unwrap();
+
+        let mut loader = IconLoader::new("theme", "theme");
+        loader.set_search_paths([root.path()]);
+
+        let icon = loader.load_icon("gnome-terminal").unwrap();
+        let file = icon.file_for_size(16);
+        assert_eq!(file.path(), Some(apps.join("gnome-terminal.png").as_path()));
+        assert_eq!(
+            file.canonical_path().unwrap(),
+            fs::canonicalize(apps.join("utilities-terminal.png")).unwrap()
+        );
+
+        let dir_info = Arc::new(IconDir::with_size("16x16/apps", 16));
+        let bytes = IconFile::from_bytes(dir_info, &b""[..], IconFileType::PNG);
+        assert_eq!(bytes.canonical_path().unwrap_err().kind(), std::io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_entry_resolver() {
+        let root = tempfile::tempdir().unwrap();
+        create_theme(
+            root.path(),
+            "assets",
+            "[Icon Theme]\nName=Assets\nInherits=hicolor\n",
+            &["app-16.png", "app-32.png", "app-32.svg", "other.png"],
+        );
+
+        let mut loader = IconLoader::new("assets", "assets");
+        loader.set_search_paths([root.path()]);
+        assert!(loader.load_icon("app").is_none());
+
+        loader.set_entry_resolver(Box::new(|theme, icon_name| {
+            let mut files = Vec::new();
+
+            for entry in fs::read_dir(theme.content_dir()).unwrap() {
+                let path = entry.unwrap().path();
+                let stem = path.file_stem().unwrap().to_str().unwrap();
+                let icon_type = IconFileType::from_path(&path);
+
+                if let (Some((name, size)), Some(icon_type)) = (stem.rsplit_once('-'), icon_type) {
+                    if name == icon_name {
+                        let dir = Arc::new(IconDir::with_size("", size.parse().unwrap()));
+                        files.push(IconFile::new(dir, path.clone(), icon_type));
+                    }
+                }
+            }
+
+            files
+        }));
+
+        let icon = loader.load_icon("app").unwrap();
+        assert_eq!(icon.available_sizes(), [16, 32]);
+        let file = icon.file_for_size(32);
+        assert_eq!(file.path(), Some(root.path().join("assets/app-32.png").as_path()));
+        assert_eq!(file.theme_root(), Some(root.path()));
+        assert!(loader.load_icon("other").is_none());
+
+        loader.set_allowed_file_types(&[IconFileType::SVG]);
+        assert_eq!(loader.load_icon("app").unwrap().files().len(), 1);
+    }
 }
@@ -1,7 +1,11 @@
 pub mod error;
 
-use std::{borrow::ToOwned, error::Error as StdError};
+use std::{borrow::ToOwned, env, error::Error as StdError, sync::Mutex};
 
+#[cfg(any(feature = "kde", feature = "gtk", feature = "xfce"))]
+use std::path::PathBuf;
+
+use crate::search_paths::EnvLookup;
 use error::{Error, Result};
 
 /// Enum that provides a theme name to [`IconLoader`](crate::IconLoader).
@@ -17,6 +21,36 @@ pub enum ThemeNameProvider {
     #[cfg(feature = "gtk")]
     GTK,
 
+    /// Use the 'kdeglobals' file in the given config directory to determine the theme name.
+    #[cfg(feature = "kde")]
+    KDEWithConfigDir(PathBuf),
+
+    /// Use the 'gtk-3.0/settings.ini' file in the given config directory to determine the theme name.
+    #[cfg(feature = "gtk")]
+    GTKWithConfigDir(PathBuf),
+
+    /// Use the '~/.config/xfce4/xfconf/xfce-perchannel-xml/xsettings.xml' file to determine the theme name.
+    #[cfg(feature = "xfce")]
+    XFCE,
+
+    /// Use the 'xfce4/xfconf/xfce-perchannel-xml/xsettings.xml' file in the given config directory
+    /// to determine the theme name.
+    #[cfg(feature = "xfce")]
+    XFCEWithConfigDir(PathBuf),
+
+    /// Use the `icon-theme` key of the `org.gnome.desktop.interface` schema to determine the theme name,
+    /// by running `gsettings get org.gnome.desktop.interface icon-theme`.
+    /// Results in [`ProviderError::ConfigNotFound`](crate::ProviderError::ConfigNotFound)
+    /// if the command is not available or fails.
+    #[cfg(feature = "gsettings")]
+    GSettings,
+
+    /// Use `$XDG_CURRENT_DESKTOP` to decide whether to read the theme name like
+    /// [`ThemeNameProvider::KDE`] or [`ThemeNameProvider::GTK`], or like `ThemeNameProvider::XFCE`
+    /// on XFCE if the `xfce` feature is enabled and like `ThemeNameProvider::GSettings` on GNOME
+    /// if the `gsettings` feature is enabled. Unknown desktops result in [`ProviderError::ConfigNotFound`](crate::ProviderError::ConfigNotFound).
+    XdgCurrentDesktop,
+
     /// A theme name provided by the user.
     User(String),
 
@@ -24,6 +58,14 @@ pub enum ThemeNameProvider {
     Custom(
         Box<dyn Fn() -> std::result::Result<String, Box<dyn StdError + Send + Sync>> + Send + Sync>,
     ),
+
+    /// A custom function like [`ThemeNameProvider::Custom`], whose first successful result is remembered.
+    /// Unlike [`ThemeNameProvider::Custom`], changes of the theme name are not noticed until
+    /// [`ThemeNameProvider::invalidate()`] is called.
+    CachedCustom(
+        Box<dyn Fn() -> std::result::Result<String, Box<dyn StdError + Send + Sync>> + Send + Sync>,
+        Mutex<Option<String>>,
+    ),
 }
 
 impl ThemeNameProvider {
@@ -32,6 +74,27 @@ impl ThemeNameProvider {
         ThemeNameProvider::User(string.into())
     }
 
+    /// Creates a new `ThemeNameProvider` that reads the KDE config from `config_dir`
+    /// instead of the XDG config directories.
+    #[cfg(feature = "kde")]
+    pub fn kde_with_config_dir(config_dir: impl Into<PathBuf>) -> Self {
+        ThemeNameProvider::KDEWithConfigDir(config_dir.into())
+    }
+
+    /// Creates a new `ThemeNameProvider` that reads the GTK config from `config_dir`
+    /// instead of the XDG config directories.
+    #[cfg(feature = "gtk")]
+    pub fn gtk_with_config_dir(config_dir: impl Into<PathBuf>) -> Self {
+        ThemeNameProvider::GTKWithConfigDir(config_dir.into())
+    }
+
+    /// Creates a new `ThemeNameProvider` that reads the XFCE config from `config_dir`
+    /// instead of the XDG config directories.
+    #[cfg(feature = "xfce")]
+    pub fn xfce_with_config_dir(config_dir: impl Into<PathBuf>) -> Self {
+        ThemeNameProvider::XFCEWithConfigDir(config_dir.into())
+    }
+
     /// Creates a new custom `ThemeNameProvider` from the given function.
     pub fn custom<F, S, E>(f: F) -> Self
     where
@@ -42,62 +105,253 @@ impl ThemeNameProvider {
         ThemeNameProvider::Custom(Box::new(move || f().map(Into::into).map_err(Into::into)))
     }
 
+    /// Creates a new custom `ThemeNameProvider` from the given function, which is only called
+    /// until it succeeds once. The theme name it returned is used from then on,
+    /// until [`ThemeNameProvider::invalidate()`] is called.
+    /// Use this for expensive functions, e.g. ones reading a remote config.
+    pub fn cached_custom<F, S, E>(f: F) -> Self
+    where
+        F: Fn() -> std::result::Result<S, E> + Send + Sync + 'static,
+        S: Into<String>,
+        E: StdError + Send + Sync + 'static,
+    {
+        ThemeNameProvider::CachedCustom(
+            Box::new(move || f().map(Into::into).map_err(Into::into)),
+            Mutex::new(None),
+        )
+    }
+
+    /// Forgets the theme name remembered by a [`ThemeNameProvider::CachedCustom`] provider,
+    /// so its function is called again the next time the theme name is queried.
+    /// Does nothing for other providers.
+    pub fn invalidate(&self) {
+        if let ThemeNameProvider::CachedCustom(_, cached) = self {
+            *cached.lock().unwrap() = None;
+        }
+    }
+
+    /// Returns the provider [`ThemeNameProvider::XdgCurrentDesktop`] delegates to for the desktops
+    /// in `$XDG_CURRENT_DESKTOP`, looked up with `var`, or `None` if none of them is known.
+    pub(crate) fn for_current_desktop(var: EnvLookup<'_>) -> Option<ThemeNameProvider> {
+        let desktops = var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        let desktops = desktops.to_str().unwrap_or_default();
+
+        // The variable may contain a colon separated list of desktop names.
+        for desktop in desktops.split(':') {
+            match desktop.to_ascii_uppercase().as_str() {
+                #[cfg(feature = "kde")]
+                "KDE" => return Some(ThemeNameProvider::KDE),
+
+                #[cfg(feature = "xfce")]
+                "XFCE" => return Some(ThemeNameProvider::XFCE),
+
+                #[cfg(all(feature = "gtk", not(feature = "xfce")))]
+                "XFCE" => return Some(ThemeNameProvider::GTK),
+
+                #[cfg(feature = "gsettings")]
+                "GNOME" => return Some(ThemeNameProvider::GSettings),
+
+                #[cfg(all(feature = "gtk", not(feature = "gsettings")))]
+                "GNOME" => return Some(ThemeNameProvider::GTK),
+
+                #[cfg(feature = "gtk")]
+                "UNITY" | "CINNAMON" | "MATE" | "BUDGIE" | "PANTHEON" | "LXDE" => {
+                    return Some(ThemeNameProvider::GTK)
+                }
+
+                _ => {}
+            }
+        }
+
+        None
+    }
+
     pub(crate) fn theme_name(&self) -> Result<String> {
         match self {
-            #[cfg(feature = "kde")]
+            #[cfg(all(feature = "kde", unix))]
             ThemeNameProvider::KDE => {
                 let base_dirs = xdg::BaseDirectories::new()?;
 
-                if base_dirs.find_config_file("kdeglobals").is_none() {
-                    return Err(Error::ConfigNotFound);
-                }
+                kde_theme_name(base_dirs.find_config_files("kdeglobals"))
+            }
 
-                for config_path in base_dirs.find_config_files("kdeglobals") {
-                    let config = ini::Ini::load_from_file(config_path)?;
-
-                    for (category, properties) in config.iter() {
-                        if let Some("Icons") = category {
-                            for (key, value) in properties.iter() {
-                                if key == "Theme" {
-                                    return Ok(value.to_string());
-                                }
-                            }
-                        }
-                    }
-                }
+            #[cfg(all(feature = "gtk", unix))]
+            ThemeNameProvider::GTK => {
+                let base_dirs = xdg::BaseDirectories::new()?;
+
+                gtk_theme_name(base_dirs.find_config_files("gtk-3.0/settings.ini"))
+            }
 
-                Err(Error::ConfigMissingThemeName)
+            #[cfg(feature = "kde")]
+            ThemeNameProvider::KDEWithConfigDir(config_dir) => {
+                kde_theme_name(existing_file(config_dir.join("kdeglobals")))
             }
 
             #[cfg(feature = "gtk")]
-            ThemeNameProvider::GTK => {
+            ThemeNameProvider::GTKWithConfigDir(config_dir) => {
+                gtk_theme_name(existing_file(config_dir.join("gtk-3.0/settings.ini")))
+            }
+
+            #[cfg(all(feature = "xfce", unix))]
+            ThemeNameProvider::XFCE => {
                 let base_dirs = xdg::BaseDirectories::new()?;
 
-                if base_dirs.find_config_file("gtk-3.0/settings.ini").is_none() {
-                    return Err(Error::ConfigNotFound);
-                }
+                xfce_theme_name(base_dirs.find_config_files(XFCE_CONFIG_PATH))
+            }
 
-                for config_path in base_dirs.find_config_files("gtk-3.0/settings.ini") {
-                    let config = ini::Ini::load_from_file(config_path)?;
-
-                    for (category, properties) in config.iter() {
-                        if let Some("Settings") = category {
-                            for (key, value) in properties.iter() {
-                                if key == "gtk-icon-theme-name" {
-                                    return Ok(value.to_string());
-                                }
-                            }
-                        }
-                    }
-                }
+            #[cfg(feature = "xfce")]
+            ThemeNameProvider::XFCEWithConfigDir(config_dir) => {
+                xfce_theme_name(existing_file(config_dir.join(XFCE_CONFIG_PATH)))
+            }
+
+            #[cfg(feature = "gsettings")]
+            ThemeNameProvider::GSettings => gsettings_theme_name("gsettings".as_ref()),
 
-                Err(Error::ConfigMissingThemeName)
+            #[cfg(all(feature = "kde", not(unix)))]
+            ThemeNameProvider::KDE => Err(Error::ConfigNotFound),
+
+            #[cfg(all(feature = "gtk", not(unix)))]
+            ThemeNameProvider::GTK => Err(Error::ConfigNotFound),
+
+            #[cfg(all(feature = "xfce", not(unix)))]
+            ThemeNameProvider::XFCE => Err(Error::ConfigNotFound),
+
+            ThemeNameProvider::XdgCurrentDesktop => {
+                match Self::for_current_desktop(&|key| env::var_os(key)) {
+                    Some(provider) => provider.theme_name(),
+                    None => Err(Error::ConfigNotFound),
+                }
             }
 
             ThemeNameProvider::User(string) => Ok(string.clone()),
             ThemeNameProvider::Custom(func) => func().map_err(|source| Error::Custom { source }),
+            ThemeNameProvider::CachedCustom(func, cached) => {
+                let mut cached = cached.lock().unwrap();
+
+                if let Some(theme_name) = &*cached {
+                    return Ok(theme_name.clone());
+                }
+
+                let theme_name = func().map_err(|source| Error::Custom { source })?;
+                *cached = Some(theme_name.clone());
+
+                Ok(theme_name)
+            }
+        }
+    }
+}
+
+/// Returns `path` as the only config file if it exists.
+#[cfg(any(feature = "kde", feature = "gtk", feature = "xfce"))]
+fn existing_file(path: PathBuf) -> impl Iterator<Item = PathBuf> {
+    Some(path).filter(|path| path.is_file()).into_iter()
+}
+
+/// Reads the icon theme name from the first of the given `kdeglobals` files that contains one.
+#[cfg(feature = "kde")]
+fn kde_theme_name(config_paths: impl IntoIterator<Item = PathBuf>) -> Result<String> {
+    read_theme_name(config_paths, "Icons", "Theme")
+}
+
+/// Reads the icon theme name from the first of the given GTK `settings.ini` files that contains one.
+#[cfg(feature = "gtk")]
+fn gtk_theme_name(config_paths: impl IntoIterator<Item = PathBuf>) -> Result<String> {
+    read_theme_name(config_paths, "Settings", "gtk-icon-theme-name")
+}
+
+/// The path of the XFCE settings relative to a config directory.
+#[cfg(feature = "xfce")]
+const XFCE_CONFIG_PATH: &str = "xfce4/xfconf/xfce-perchannel-xml/xsettings.xml";
+
+/// Reads the icon theme name from the `/Net/IconThemeName` property of the first of the given
+/// XFCE `xsettings.xml` files that contains one.
+/// Returns [`Error::ConfigNotFound`] if there are no config files.
+#[cfg(feature = "xfce")]
+fn xfce_theme_name(config_paths: impl IntoIterator<Item = PathBuf>) -> Result<String> {
+    let mut config_paths = config_paths.into_iter().peekable();
+
+    if config_paths.peek().is_none() {
+        return Err(Error::ConfigNotFound);
+    }
+
+    for config_path in config_paths {
+        let config = std::fs::read_to_string(config_path)
+            .map_err(|source| Error::ReadConfig { source })?;
+        let document = roxmltree::Document::parse(&config)?;
+
+        if let Some(value) = xfce_property(document.root_element(), "Net")
+            .and_then(|net| xfce_property(net, "IconThemeName"))
+            .and_then(|icon_theme_name| icon_theme_name.attribute("value"))
+            .filter(|value| !value.is_empty())
+        {
+            return Ok(value.to_string());
         }
     }
+
+    Err(Error::ConfigMissingThemeName)
+}
+
+/// Returns the child `<property>` element of `node` with the given name.
+#[cfg(feature = "xfce")]
+fn xfce_property<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children()
+        .find(|child| child.has_tag_name("property") && child.attribute("name") == Some(name))
+}
+
+/// Reads the icon theme name by running `program get org.gnome.desktop.interface icon-theme`,
+/// which prints the name as a quoted GVariant string, e.g. `'Adwaita'`.
+/// Returns [`Error::ConfigNotFound`] if the program cannot be run or fails.
+#[cfg(feature = "gsettings")]
+pub(crate) fn gsettings_theme_name(program: &std::ffi::OsStr) -> Result<String> {
+    let output = std::process::Command::new(program)
+        .args(["get", "org.gnome.desktop.interface", "icon-theme"])
+        .output()
+        .map_err(|_| Error::ConfigNotFound)?;
+
+    if !output.status.success() {
+        return Err(Error::ConfigNotFound);
+    }
+
+    let value = String::from_utf8_lossy(&output.stdout);
+    let value = value.trim();
+    let theme_name = ['\'', '"']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value);
+
+    if theme_name.is_empty() {
+        return Err(Error::ConfigMissingThemeName);
+    }
+
+    Ok(theme_name.to_string())
+}
+
+/// Returns the value of `key` in `section` of the first config file that contains it.
+/// Returns [`Error::ConfigNotFound`] if there are no config files.
+#[cfg(any(feature = "kde", feature = "gtk"))]
+fn read_theme_name(
+    config_paths: impl IntoIterator<Item = PathBuf>,
+    section: &str,
+    key: &str,
+) -> Result<String> {
+    let mut config_paths = config_paths.into_iter().peekable();
+
+    if config_paths.peek().is_none() {
+        return Err(Error::ConfigNotFound);
+    }
+
+    for config_path in config_paths {
+        let config = ini::Ini::load_from_file(config_path)?;
+
+        if let Some(value) = config.get_from(Some(section), key) {
+            return Ok(value.to_string());
+        }
+    }
+
+    Err(Error::ConfigMissingThemeName)
 }
 
 impl std::fmt::Debug for ThemeNameProvider {
@@ -109,8 +363,31 @@ impl std::fmt::Debug for ThemeNameProvider {
             #[cfg(feature = "gtk")]
             ThemeNameProvider::GTK => write!(f, "ThemeNameProvider::GTK"),
 
+            #[cfg(feature = "kde")]
+            ThemeNameProvider::KDEWithConfigDir(config_dir) => {
+                write!(f, "ThemeNameProvider::KDEWithConfigDir({})", config_dir.display())
+            }
+
+            #[cfg(feature = "gtk")]
+            ThemeNameProvider::GTKWithConfigDir(config_dir) => {
+                write!(f, "ThemeNameProvider::GTKWithConfigDir({})", config_dir.display())
+            }
+
+            #[cfg(feature = "xfce")]
+            ThemeNameProvider::XFCE => write!(f, "ThemeNameProvider::XFCE"),
+
+            #[cfg(feature = "gsettings")]
+            ThemeNameProvider::GSettings => write!(f, "ThemeNameProvider::GSettings"),
+
+            #[cfg(feature = "xfce")]
+            ThemeNameProvider::XFCEWithConfigDir(config_dir) => {
+                write!(f, "ThemeNameProvider::XFCEWithConfigDir({})", config_dir.display())
+            }
+
+            ThemeNameProvider::XdgCurrentDesktop => write!(f, "ThemeNameProvider::XdgCurrentDesktop"),
             ThemeNameProvider::User(string) => write!(f, "ThemeNameProvider::User({})", string),
             ThemeNameProvider::Custom(_) => write!(f, "ThemeNameProvider::Custom"),
+            ThemeNameProvider::CachedCustom(..) => write!(f, "ThemeNameProvider::CachedCustom"),
         }
     }
 }
@@ -129,17 +406,32 @@ impl PartialEq for ThemeNameProvider {
             return false;
         }
 
-        if let ThemeNameProvider::Custom(_) = self {
+        if let ThemeNameProvider::Custom(_) | ThemeNameProvider::CachedCustom(..) = self {
             return false;
         }
 
-        if let (ThemeNameProvider::User(string), ThemeNameProvider::User(other_string)) =
-            (self, other)
-        {
-            return string == other_string;
-        }
+        match (self, other) {
+            (ThemeNameProvider::User(string), ThemeNameProvider::User(other_string)) => {
+                string == other_string
+            }
 
-        true
+            #[cfg(feature = "kde")]
+            (ThemeNameProvider::KDEWithConfigDir(dir), ThemeNameProvider::KDEWithConfigDir(other_dir)) => {
+                dir == other_dir
+            }
+
+            #[cfg(feature = "gtk")]
+            (ThemeNameProvider::GTKWithConfigDir(dir), ThemeNameProvider::GTKWithConfigDir(other_dir)) => {
+                dir == other_dir
+            }
+
+            #[cfg(feature = "xfce")]
+            (ThemeNameProvider::XFCEWithConfigDir(dir), ThemeNameProvider::XFCEWithConfigDir(other_dir)) => {
+                dir == other_dir
+            }
+
+            _ => true,
+        }
     }
 }
 
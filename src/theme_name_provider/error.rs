@@ -16,10 +16,24 @@ pub enum Error {
         source: ini::Error,
     },
 
+    /// Error reading a config file.
+    ReadConfig {
+        /// The source for the error.
+        source: std::io::Error,
+    },
+
+    /// Error parsing an XML config file.
+    #[cfg(feature = "xfce")]
+    ParseXml {
+        /// The source for the error.
+        source: roxmltree::Error,
+    },
+
     /// Config does not contain valid theme name.
     ConfigMissingThemeName,
 
     /// Error originating in the `xdg` crate.
+    #[cfg(unix)]
     XDG {
         /// The source for the error.
         source: xdg::BaseDirectoriesError,
@@ -35,8 +49,12 @@ pub enum Error {
 impl StdError for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
+            #[cfg(unix)]
             Error::XDG { source } => Some(source),
             Error::LoadConfig { source } => Some(source),
+            Error::ReadConfig { source } => Some(source),
+            #[cfg(feature = "xfce")]
+            Error::ParseXml { source } => Some(source),
             Error::Custom { source } => Some(source.as_ref()),
             _ => None,
         }
@@ -48,9 +66,13 @@ impl fmt::Display for Error {
         match self {
             Error::ConfigNotFound => write!(f, "Config file could not be found."),
             Error::LoadConfig { source } => write!(f, "Error loading config file: {}", source),
+            Error::ReadConfig { source } => write!(f, "Error reading config file: {}", source),
+            #[cfg(feature = "xfce")]
+            Error::ParseXml { source } => write!(f, "Error parsing XML config file: {}", source),
             Error::ConfigMissingThemeName => {
                 write!(f, "Config file is missing a valid theme name.")
             }
+            #[cfg(unix)]
             Error::XDG { source } => write!(f, "Error loading XDG locations: {}", source),
             Error::Custom { source } => {
                 write!(f, "Error in custom theme name provider: {}", source)
@@ -65,6 +87,14 @@ impl From<ini::Error> for Error {
     }
 }
 
+#[cfg(feature = "xfce")]
+impl From<roxmltree::Error> for Error {
+    fn from(source: roxmltree::Error) -> Self {
+        Error::ParseXml { source }
+    }
+}
+
+#[cfg(unix)]
 impl From<xdg::BaseDirectoriesError> for Error {
     fn from(source: xdg::BaseDirectoriesError) -> Self {
         Error::XDG { source }
@@ -0,0 +1,98 @@
+use crate::{error::Result, ThemeCache};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{mpsc, Arc, RwLock, Weak},
+    thread,
+    time::Duration,
+};
+
+/// How long to wait for further changes before evicting themes.
+const DEBOUNCE_DURATION: Duration = Duration::from_millis(500);
+
+/// The theme cache a loader currently uses. The loader replaces it when its theme options change,
+/// so that the watcher keeps evicting from the live cache.
+pub(crate) type CacheSlot = Arc<RwLock<Weak<ThemeCache>>>;
+
+/// Guard returned by [`IconLoader::enable_auto_reload()`](crate::IconLoader::enable_auto_reload).
+/// Watching the search paths stops when it is dropped.
+pub struct WatchGuard {
+    _watcher: RecommendedWatcher,
+}
+
+impl std::fmt::Debug for WatchGuard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WatchGuard")
+    }
+}
+
+pub(crate) fn watch(cache: &Arc<ThemeCache>, slot: &CacheSlot) -> Result<WatchGuard> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    let search_paths = cache.search_paths().into_owned();
+
+    for search_path in search_paths.iter().filter(|path| path.is_dir()) {
+        watcher.watch(search_path, RecursiveMode::Recursive)?;
+    }
+
+    *slot.write().unwrap() = Arc::downgrade(cache);
+    let slot = Arc::clone(slot);
+
+    // The thread ends once the watcher and with it the sending half of the channel is dropped.
+    thread::spawn(move || evict_changed_themes(&slot, &search_paths, &receiver));
+
+    Ok(WatchGuard { _watcher: watcher })
+}
+
+/// Evicts the themes changed by the events from `receiver` from the cache in `slot`
+/// until the sending half of the channel is dropped or the cache is gone.
+pub(crate) fn evict_changed_themes(
+    slot: &CacheSlot,
+    search_paths: &[PathBuf],
+    receiver: &mpsc::Receiver<notify::Result<Event>>,
+) {
+    while let Ok(event) = receiver.recv() {
+        let mut theme_names = HashSet::new();
+        collect_theme_names(event, search_paths, &mut theme_names);
+
+        while let Ok(event) = receiver.recv_timeout(DEBOUNCE_DURATION) {
+            collect_theme_names(event, search_paths, &mut theme_names);
+        }
+
+        let cache = match slot.read().unwrap().upgrade() {
+            Some(cache) => cache,
+            None => return,
+        };
+
+        for theme_name in theme_names {
+            cache.evict(&theme_name);
+        }
+    }
+}
+
+/// Adds the names of the themes containing the paths of `event` to `theme_names`.
+fn collect_theme_names(
+    event: notify::Result<Event>,
+    search_paths: &[PathBuf],
+    theme_names: &mut HashSet<String>,
+) {
+    let event = match event {
+        Ok(event) => event,
+        Err(_) => return,
+    };
+
+    for path in event.paths {
+        let theme_name = search_paths
+            .iter()
+            .find_map(|search_path| path.strip_prefix(search_path).ok())
+            .and_then(|relative_path| relative_path.components().next())
+            .and_then(|theme_dir| theme_dir.as_os_str().to_str());
+
+        if let Some(theme_name) = theme_name {
+            theme_names.insert(theme_name.to_string());
+        }
+    }
+}